@@ -0,0 +1,36 @@
+use aoc2024::day1::{part1_comparison, part1_radix};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use proptest::{
+    prelude::RngExt,
+    test_runner::{RngAlgorithm, TestRng},
+};
+
+fn generate(lines: usize) -> String {
+    let mut rng = TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+    let mut next = move || rng.random_range(10000..100000u32);
+
+    (0..lines)
+        .map(|_| format!("{}   {}\n", next(), next()))
+        .collect()
+}
+
+fn part1_crossover(c: &mut Criterion) {
+    let mut group = c.benchmark_group("day1_part1");
+    for lines in [
+        1_000, 2_000, 5_000, 10_000, 15_000, 20_000, 30_000, 50_000, 100_000, 200_000, 500_000,
+        1_000_000,
+    ] {
+        let input = generate(lines);
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_with_input(BenchmarkId::new("comparison", lines), &input, |b, input| {
+            b.iter(|| part1_comparison(input).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("radix", lines), &input, |b, input| {
+            b.iter(|| part1_radix(input).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, part1_crossover);
+criterion_main!(benches);
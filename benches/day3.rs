@@ -0,0 +1,23 @@
+use aoc2024::day3::{generate, part2, part2_many_till};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+fn part2_scanners(c: &mut Criterion) {
+    let mut group = c.benchmark_group("day3_part2");
+    for bytes in [20_000, 1_000_000, 10_000_000] {
+        let generated = generate(0x2545F4914F6CDD1D, bytes);
+        let input = generated.memory;
+        assert_eq!(part2(&input), Ok(generated.part2));
+        assert_eq!(part2_many_till(&input), Ok(generated.part2));
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_with_input(BenchmarkId::new("candidates", bytes), &input, |b, input| {
+            b.iter(|| part2(input))
+        });
+        group.bench_with_input(BenchmarkId::new("many_till", bytes), &input, |b, input| {
+            b.iter(|| part2_many_till(input))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, part2_scanners);
+criterion_main!(benches);
@@ -0,0 +1,82 @@
+use aoc2024::day4::{part1, part1_bitboard, part2, part2_bitboard, Cell, Grid};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use proptest::{
+    prelude::RngExt,
+    test_runner::{RngAlgorithm, TestRng},
+};
+
+fn generate(size: usize) -> String {
+    let mut rng = TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+    let mut next = move || rng.random::<u64>();
+
+    let mut input = String::with_capacity(size * (size + 1));
+    for _ in 0..size {
+        input.extend((0..size).map(|_| char::from(b"XMAS"[(next() % 4) as usize])));
+        input.push('\n');
+    }
+    input
+}
+
+/// The unvalidated parser `Grid::parse` replaced.
+fn parse_transmute(input: &str) -> (Vec<Cell>, usize) {
+    // SAFETY: `Cell` is a transparent wrapper around a byte.
+    let mut letters = Vec::from(unsafe { std::mem::transmute::<&[u8], &[Cell]>(input.as_bytes()) });
+    let width = letters
+        .iter()
+        .position(|s| s.byte == b'\n')
+        .unwrap_or(letters.len());
+    letters.retain(|s| s.byte != b'\n');
+    (letters, width)
+}
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("day4_parse");
+    for size in [140, 1000, 4000] {
+        let input = generate(size);
+        assert_eq!(
+            Grid::parse(&input).unwrap().len(),
+            parse_transmute(&input).0.len()
+        );
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_with_input(BenchmarkId::new("validated", size), &input, |b, input| {
+            b.iter(|| Grid::parse(input).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("transmute", size), &input, |b, input| {
+            b.iter(|| parse_transmute(input))
+        });
+    }
+    group.finish();
+}
+
+fn engines(c: &mut Criterion) {
+    let mut group = c.benchmark_group("day4_engines");
+    group.sample_size(10);
+    for size in [1000, 2000, 4000] {
+        let grid = Grid::parse(&generate(size)).unwrap();
+        assert_eq!(part1(&grid), part1_bitboard(&grid));
+        assert_eq!(part2(&grid), part2_bitboard(&grid));
+        group.throughput(Throughput::Elements(grid.len() as u64));
+        group.bench_with_input(BenchmarkId::new("part1_search", size), &grid, |b, grid| {
+            b.iter(|| part1(grid))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("part1_bitboard", size),
+            &grid,
+            |b, grid| b.iter(|| part1_bitboard(grid)),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("part2_template", size),
+            &grid,
+            |b, grid| b.iter(|| part2(grid)),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("part2_bitboard", size),
+            &grid,
+            |b, grid| b.iter(|| part2_bitboard(grid)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, parse, engines);
+criterion_main!(benches);
@@ -1,139 +1,519 @@
 use aoc_runner_derive::aoc;
 use arrayvec::ArrayVec;
+use std::{fmt::Display, ops::Deref};
 
-#[inline(always)]
-pub fn diff_in_range(diff: i32) -> bool {
-    let abs = diff.abs();
-    abs >= 1 && abs <= 3
+/// Which way the levels of a report are allowed to go.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Trend {
+    Increasing,
+    Decreasing,
+    Either,
 }
 
-#[inline(always)]
-pub fn diff_same_monotonicity(diff_a: i32, diff_b: i32) -> bool {
-    diff_a.signum() == diff_b.signum()
+/// What makes a report safe. The default is the puzzle's: every step is between one and three,
+/// and the levels are either all strictly increasing or all strictly decreasing.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct SafetyRules {
+    pub min_step: u64,
+    pub max_step: u64,
+    /// Whether two adjacent levels may not be equal.
+    pub strict: bool,
+    pub trend: Trend,
 }
 
-#[aoc(day2, part1)]
-pub fn part1(input: &str) -> u32 {
-    let mut safe_count = 0;
-    let lines = input.lines();
+impl Default for SafetyRules {
+    fn default() -> Self {
+        SafetyRules {
+            min_step: 1,
+            max_step: 3,
+            strict: true,
+            trend: Trend::Either,
+        }
+    }
+}
+
+/// Why a report is unsafe.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Violation {
+    /// Two adjacent levels differ by less than `min_step` or more than `max_step`.
+    StepOutOfRange,
+    /// The levels switched between increasing and decreasing.
+    MonotonicityFlip,
+    /// Two adjacent levels are equal under strict monotonicity.
+    Unchanged,
+    /// The levels go the way the `Trend` does not allow.
+    WrongDirection,
+}
+
+/// The first rule a report breaks.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Failure {
+    pub violation: Violation,
+    /// Index of the level that broke the rule, the second of `pair`.
+    pub index: usize,
+    /// The two adjacent levels that broke the rule.
+    pub pair: (i64, i64),
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self.violation {
+            Violation::StepOutOfRange => "step out of range",
+            Violation::MonotonicityFlip => "monotonicity flip",
+            Violation::Unchanged => "unchanged level",
+            Violation::WrongDirection => "wrong direction",
+        };
+        write!(
+            f,
+            "{reason} at level {}: {} -> {}",
+            self.index, self.pair.0, self.pair.1
+        )
+    }
+}
+
+/// A proposed correction for an unsafe report.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Repair {
+    /// Indices of the levels that were deleted or overwritten, in ascending order.
+    pub changed: Vec<usize>,
+    /// The report after the repair.
+    pub levels: Vec<i64>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ReportVerdict {
+    Safe,
+    /// Unsafe as reported, but safe once the Problem Dampener removes the level at index `removed`.
+    Dampened {
+        removed: usize,
+        failure: Failure,
+    },
+    Unsafe(Failure),
+}
+
+impl ReportVerdict {
+    pub fn is_safe(&self) -> bool {
+        !matches!(self, ReportVerdict::Unsafe(_))
+    }
+}
+
+/// A level that could not be parsed, pointing at its line (1-based) and column (1-based).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub token: String,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid level at line {}, column {}: {:?}",
+            self.line, self.column, self.token
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Reports up to this long are parsed without allocating.
+const INLINE_LEVELS: usize = 16;
+
+/// The levels of a single report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Levels {
+    Inline(ArrayVec<i64, INLINE_LEVELS>),
+    Heap(Vec<i64>),
+}
+
+impl Levels {
+    /// Parses the whitespace separated levels on a line. `line_number` is only used for errors.
+    pub fn parse(line_number: usize, line: &str) -> Result<Levels, ParseError> {
+        let mut levels = Levels::Inline(ArrayVec::new());
+        for token in line.split_whitespace() {
+            let level = token.parse::<i64>().map_err(|_| ParseError {
+                line: line_number,
+                column: line[..token.as_ptr() as usize - line.as_ptr() as usize]
+                    .chars()
+                    .count()
+                    + 1,
+                token: token.to_string(),
+            })?;
+
+            match &mut levels {
+                Levels::Inline(inline) => {
+                    if let Err(overflow) = inline.try_push(level) {
+                        let mut heap = Vec::with_capacity(INLINE_LEVELS * 2);
+                        heap.extend_from_slice(inline);
+                        heap.push(overflow.element());
+                        levels = Levels::Heap(heap);
+                    }
+                }
+                Levels::Heap(heap) => heap.push(level),
+            }
+        }
+
+        Ok(levels)
+    }
+}
+
+impl Deref for Levels {
+    type Target = [i64];
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Levels::Inline(inline) => inline,
+            Levels::Heap(heap) => heap,
+        }
+    }
+}
+
+/// Parses every non-blank line of the input as a report.
+fn reports(input: &str) -> impl Iterator<Item = Result<Levels, ParseError>> + '_ {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| Levels::parse(index + 1, line))
+}
+
+#[inline(always)]
+fn without(levels: &[i64], removed: usize) -> impl Iterator<Item = (usize, i64)> + '_ {
+    levels
+        .iter()
+        .copied()
+        .enumerate()
+        .filter(move |&(index, _)| index != removed)
+}
 
-    'report: for line in lines {
-        let mut levels = line.split(" ").map(|s| s.parse::<i32>().unwrap());
+impl SafetyRules {
+    #[inline(always)]
+    pub fn diff_in_range(&self, previous: i64, next: i64) -> bool {
+        (self.min_step..=self.max_step).contains(&previous.abs_diff(next))
+    }
 
-        let p1 = levels.next().unwrap();
-        let p2 = levels.next().unwrap();
+    /// Checks the step from `previous` to `next` against the `direction` established by the steps
+    /// before it, or zero if there is none yet. Returns the direction after this step.
+    #[inline(always)]
+    fn check_step(&self, direction: i32, previous: i64, next: i64) -> Result<i32, Violation> {
+        if !self.diff_in_range(previous, next) {
+            return Err(Violation::StepOutOfRange);
+        }
 
-        let mut current_diff = p1 - p2;
-        if !diff_in_range(current_diff) {
-            // unsafe
-            continue 'report;
+        let sign = next.cmp(&previous) as i32;
+        match (sign, self.trend) {
+            (0, _) if self.strict => Err(Violation::Unchanged),
+            (0, _) => Ok(direction),
+            (1, Trend::Decreasing) | (-1, Trend::Increasing) => Err(Violation::WrongDirection),
+            _ if direction != 0 && sign != direction => Err(Violation::MonotonicityFlip),
+            _ => Ok(sign),
         }
+    }
 
-        let mut current = p2;
+    /// Checks a report given as `(index, level)` pairs, returning the first failure if it is unsafe.
+    #[inline(always)]
+    fn subset_is_good_until(
+        &self,
+        subset: impl IntoIterator<Item = (usize, i64)>,
+    ) -> Option<Failure> {
+        let mut levels = subset.into_iter();
+        let (_, mut current) = levels.next()?;
+        let mut direction = 0;
 
-        for level in levels {
-            let new_diff = current - level;
-            if !diff_in_range(new_diff) || !diff_same_monotonicity(current_diff, new_diff) {
-                // unsafe
-                continue 'report;
+        for (index, level) in levels {
+            match self.check_step(direction, current, level) {
+                Ok(new_direction) => direction = new_direction,
+                Err(violation) => {
+                    return Some(Failure {
+                        violation,
+                        index,
+                        pair: (current, level),
+                    })
+                }
             }
 
-            current_diff = new_diff;
             current = level;
         }
 
-        safe_count += 1;
+        None
     }
 
-    safe_count
-}
+    pub fn verdict(&self, levels: &[i64]) -> ReportVerdict {
+        match self.subset_is_good_until(levels.iter().copied().enumerate()) {
+            Some(failure) => ReportVerdict::Unsafe(failure),
+            None => ReportVerdict::Safe,
+        }
+    }
 
-#[inline(always)]
-fn subset_is_good_until(subset: &[i32]) -> Option<usize> {
-    if subset.len() <= 1 {
-        return None;
+    /// Like `verdict`, but lets the Problem Dampener remove a single level.
+    pub fn dampened_verdict(&self, levels: &[i64]) -> ReportVerdict {
+        let Some(failure) = self.subset_is_good_until(levels.iter().copied().enumerate()) else {
+            return ReportVerdict::Safe;
+        };
+
+        // Either edge, or one of the levels around the first failure.
+        let index = failure.index;
+        for removed in [0, levels.len() - 1, index, index - 1, index + 1] {
+            if removed < levels.len()
+                && self
+                    .subset_is_good_until(without(levels, removed))
+                    .is_none()
+            {
+                return ReportVerdict::Dampened { removed, failure };
+            }
+        }
+
+        ReportVerdict::Unsafe(failure)
     }
 
-    let p1 = subset[0];
-    let p2 = subset[1];
-    let mut current_diff = p1 - p2;
+    /// The indices of the longest safe subsequence whose steps all go in `direction` or stay level.
+    ///
+    /// Dynamic programming over the index the subsequence ends at, in `O(n^2)`.
+    fn longest_safe_subsequence(&self, levels: &[i64], direction: i32) -> Vec<usize> {
+        // (length of the longest subsequence ending here, previous index in it)
+        let mut longest: Vec<(usize, Option<usize>)> = Vec::with_capacity(levels.len());
+        for (index, &level) in levels.iter().enumerate() {
+            let extended = (0..index)
+                .filter(|&prev| self.check_step(direction, levels[prev], level).is_ok())
+                .map(|prev| (longest[prev].0 + 1, Some(prev)))
+                .max_by_key(|&(length, _)| length);
+            longest.push(extended.unwrap_or((1, None)));
+        }
 
-    if !diff_in_range(current_diff) {
-        return Some(1);
+        let mut kept = Vec::new();
+        let mut current = (0..levels.len()).max_by_key(|&index| longest[index].0);
+        while let Some(index) = current {
+            kept.push(index);
+            current = longest[index].1;
+        }
+        kept.reverse();
+        kept
     }
 
-    let mut current = p2;
+    /// The smallest set of level indices whose removal makes the report safe, in ascending order.
+    pub fn minimal_removals(&self, levels: &[i64]) -> Vec<usize> {
+        let directions: &[i32] = match self.trend {
+            Trend::Increasing => &[1],
+            Trend::Decreasing => &[-1],
+            Trend::Either => &[-1, 1],
+        };
+
+        let kept = directions
+            .iter()
+            .map(|&direction| self.longest_safe_subsequence(levels, direction))
+            .max_by_key(|kept| kept.len())
+            .unwrap_or_default();
 
-    for (index, level) in subset[2..].iter().enumerate() {
-        let new_diff = current - level;
-        if !diff_in_range(new_diff) || !diff_same_monotonicity(current_diff, new_diff) {
-            return Some(index + 2);
+        (0..levels.len())
+            .filter(|index| kept.binary_search(index).is_err())
+            .collect()
+    }
+
+    /// Whether removing at most `k` levels makes the report safe, returning the smallest such removal set.
+    ///
+    /// With `k = 1` this is the Problem Dampener from part 2.
+    pub fn is_safe_with_removals(&self, levels: &[i64], k: usize) -> Option<Vec<usize>> {
+        let removals = self.minimal_removals(levels);
+        (removals.len() <= k).then_some(removals)
+    }
+
+    /// The cheapest repair that deletes levels, see `minimal_removals`.
+    pub fn repair_by_deletion(&self, levels: &[i64]) -> Repair {
+        let changed = self.minimal_removals(levels);
+        let repaired = levels
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| changed.binary_search(index).is_err())
+            .map(|(_, &level)| level)
+            .collect();
+
+        Repair {
+            changed,
+            levels: repaired,
         }
+    }
 
-        current_diff = new_diff;
-        current = *level;
+    /// The smallest and largest size of a step that may follow a step in the same direction.
+    #[inline(always)]
+    fn step_bounds(&self) -> (i128, i128) {
+        let min = if self.strict {
+            self.min_step.max(1)
+        } else {
+            self.min_step
+        };
+        (min as i128, self.max_step as i128)
     }
 
-    None
-}
+    /// The cheapest repair that overwrites levels in place, or `None` if no report of this length
+    /// can be safe under these rules.
+    ///
+    /// Two levels `gap` apart can both be kept when their difference can be made up by `gap` steps
+    /// going in the same direction, so this finds the longest chain of such levels by dynamic
+    /// programming, and then fills in the rest by spreading each difference evenly. Chains whose
+    /// padding at either end would leave the `i64` range are not considered.
+    pub fn repair_by_overwrite(&self, levels: &[i64]) -> Option<Repair> {
+        let (min, max) = self.step_bounds();
+        if levels.len() <= 1 {
+            return Some(Repair {
+                changed: vec![],
+                levels: levels.to_vec(),
+            });
+        } else if min > max {
+            return None;
+        }
 
-#[aoc(day2, part2)]
-pub fn part2(input: &str) -> u32 {
-    let mut safe_count = 0;
-    let lines = input.lines();
+        let directions: &[i128] = match self.trend {
+            Trend::Increasing => &[1],
+            Trend::Decreasing => &[-1],
+            Trend::Either => &[-1, 1],
+        };
 
-    // let mut unsafe_levels = Vec::with_capacity(1000);
+        let mut best: Option<Repair> = None;
+        for &direction in directions {
+            // Work on `direction * level`, so the chain always goes up and the padding before
+            // its first level and after its last one goes down and up by `min` respectively.
+            let value = |index: usize| levels[index] as i128 * direction;
+            let (low, high) = if direction > 0 {
+                (i64::MIN as i128, i64::MAX as i128)
+            } else {
+                (-(i64::MAX as i128), -(i64::MIN as i128))
+            };
+            let can_start = |index: usize| value(index) - min * index as i128 >= low;
+            let can_end =
+                |index: usize| value(index) + min * (levels.len() - 1 - index) as i128 <= high;
 
-    'report: for line in lines {
-        let levels = line.split(" ").map(|s| s.parse::<i32>().unwrap());
+            // (length of the longest chain of kept levels ending here, previous index in it),
+            // or `None` if no chain can reach here without padding leaving the i64 range.
+            let mut longest: Vec<Option<(usize, Option<usize>)>> = Vec::with_capacity(levels.len());
+            for index in 0..levels.len() {
+                let extended = (0..index)
+                    .filter(|&prev| {
+                        let gap = (index - prev) as i128;
+                        let diff = value(index) - value(prev);
+                        (min * gap..=max * gap).contains(&diff)
+                    })
+                    .filter_map(|prev| Some((longest[prev]?.0 + 1, Some(prev))))
+                    .max_by_key(|&(length, _)| length);
+                longest.push(extended.or(can_start(index).then_some((1, None))));
+            }
 
-        let mut vec = levels.collect::<ArrayVec<i32, 12>>();
-        if let Some(index) = subset_is_good_until(&vec) {
-            // check edges
-            if subset_is_good_until(&vec[1..]).is_none() {
-                safe_count += 1;
-                continue 'report;
+            let mut kept = Vec::new();
+            let mut current = (0..levels.len())
+                .filter(|&index| can_end(index))
+                .filter_map(|index| Some((longest[index]?.0, index)))
+                .max()
+                .map(|(_, index)| index);
+            while let Some(index) = current {
+                kept.push(index);
+                current = longest[index].and_then(|(_, prev)| prev);
             }
+            kept.reverse();
 
-            if subset_is_good_until(&vec[..vec.len() - 1]).is_none() {
-                safe_count += 1;
-                continue 'report;
+            let mut repaired = vec![0i128; levels.len()];
+            match (kept.first(), kept.last()) {
+                (Some(&first), Some(&last)) => {
+                    for (index, repaired) in repaired.iter_mut().enumerate() {
+                        if index < first {
+                            *repaired = value(first) - min * (first - index) as i128;
+                        } else if index > last {
+                            *repaired = value(last) + min * (index - last) as i128;
+                        }
+                    }
+                    repaired[last] = value(last);
+                }
+                // Nothing can be kept, so overwrite everything going up from the bottom of the range.
+                _ if min * (levels.len() - 1) as i128 <= high - low => {
+                    for (index, repaired) in repaired.iter_mut().enumerate() {
+                        *repaired = low + min * index as i128;
+                    }
+                }
+                _ => continue,
+            }
+            for pair in kept.windows(2) {
+                let (start, end) = (pair[0], pair[1]);
+                let gap = (end - start) as i128;
+                let diff = value(end) - value(start);
+                let (step, remainder) = (diff / gap, diff % gap);
+                repaired[start] = value(start);
+                for offset in 1..=(end - start) {
+                    let step = if (offset as i128) <= remainder {
+                        step + 1
+                    } else {
+                        step
+                    };
+                    repaired[start + offset] = repaired[start + offset - 1] + step;
+                }
             }
 
-            let value = vec.remove(index);
-            if subset_is_good_until(&vec).is_none() {
-                safe_count += 1;
-                continue 'report;
+            let repaired: Vec<i64> = repaired
+                .into_iter()
+                .map(|value| i64::try_from(value * direction).expect("kept within i64"))
+                .collect();
+
+            debug_assert!(self
+                .subset_is_good_until(repaired.iter().copied().enumerate())
+                .is_none());
+
+            let changed: Vec<usize> = (0..levels.len())
+                .filter(|&index| repaired[index] != levels[index])
+                .collect();
+            if best
+                .as_ref()
+                .is_none_or(|best| changed.len() < best.changed.len())
+            {
+                best = Some(Repair {
+                    changed,
+                    levels: repaired,
+                });
             }
+        }
+
+        best
+    }
 
-            vec.insert(index, value);
-            let value = vec.remove(index - 1);
+    pub fn count_safe(&self, input: &str) -> Result<u32, ParseError> {
+        let mut safe_count = 0;
 
-            if subset_is_good_until(&vec).is_none() {
+        for levels in reports(input) {
+            if self.verdict(&levels?).is_safe() {
                 safe_count += 1;
-                continue 'report;
             }
+        }
+
+        Ok(safe_count)
+    }
 
-            vec.insert(index - 1, value);
-            vec.remove(index + 1);
+    pub fn count_dampened_safe(&self, input: &str) -> Result<u32, ParseError> {
+        let mut safe_count = 0;
 
-            if subset_is_good_until(&vec).is_none() {
+        for levels in reports(input) {
+            if self.dampened_verdict(&levels?).is_safe() {
                 safe_count += 1;
-                continue 'report;
             }
-            continue 'report;
         }
 
-        // println!("{} is safe", line);
-        safe_count += 1;
+        Ok(safe_count)
     }
+}
 
-    safe_count
+#[aoc(day2, part1)]
+pub fn part1(input: &str) -> Result<u32, ParseError> {
+    SafetyRules::default().count_safe(input)
+}
+
+#[aoc(day2, part2)]
+pub fn part2(input: &str) -> Result<u32, ParseError> {
+    SafetyRules::default().count_dampened_safe(input)
 }
 
 #[cfg(test)]
 mod test {
-    use crate::day2::{part1, part2};
+    use crate::day2::{
+        part1, part2, without, Failure, Levels, ParseError, Repair, ReportVerdict, SafetyRules,
+        Trend, Violation,
+    };
+    use proptest::prelude::*;
 
     #[test]
     fn part1_test() {
@@ -144,7 +524,7 @@ mod test {
 8 6 4 4 1
 1 3 6 7 9";
 
-        assert_eq!(part1(input), 2);
+        assert_eq!(part1(input), Ok(2));
     }
 
     #[test]
@@ -157,7 +537,7 @@ mod test {
 1 3 6 7 9
 ";
 
-        assert_eq!(part2(input), 4);
+        assert_eq!(part2(input), Ok(4));
     }
 
     #[test]
@@ -174,13 +554,555 @@ mod test {
 29 28 27 25 26 25 22 20
 ";
 
-        assert_eq!(part2(input), 10);
+        assert_eq!(part2(input), Ok(10));
     }
 
     #[test]
     fn part1_test_bad() {
         let input = "1 2 4 6 8 9 13";
 
-        assert_eq!(part1(input), 0);
+        assert_eq!(part1(input), Ok(0));
+    }
+
+    #[test]
+    fn verdict_test() {
+        let rules = SafetyRules::default();
+        assert_eq!(rules.verdict(&[7, 6, 4, 2, 1]), ReportVerdict::Safe);
+        assert_eq!(
+            rules.verdict(&[1, 2, 7, 8, 9]),
+            ReportVerdict::Unsafe(Failure {
+                violation: Violation::StepOutOfRange,
+                index: 2,
+                pair: (2, 7),
+            })
+        );
+
+        let failure = Failure {
+            violation: Violation::MonotonicityFlip,
+            index: 2,
+            pair: (3, 2),
+        };
+        assert_eq!(
+            rules.verdict(&[1, 3, 2, 4, 5]),
+            ReportVerdict::Unsafe(failure)
+        );
+        assert_eq!(failure.to_string(), "monotonicity flip at level 2: 3 -> 2");
+
+        assert_eq!(
+            rules.verdict(&[8, 8, 4, 4, 1]),
+            ReportVerdict::Unsafe(Failure {
+                violation: Violation::StepOutOfRange,
+                index: 1,
+                pair: (8, 8),
+            })
+        );
+        assert_eq!(rules.verdict(&[1]), ReportVerdict::Safe);
+        assert_eq!(rules.verdict(&[]), ReportVerdict::Safe);
+    }
+
+    #[test]
+    fn dampened_verdict_test() {
+        let rules = SafetyRules::default();
+        assert_eq!(
+            rules.dampened_verdict(&[7, 6, 4, 2, 1]),
+            ReportVerdict::Safe
+        );
+        assert_eq!(
+            rules.dampened_verdict(&[1, 3, 2, 4, 5]),
+            ReportVerdict::Dampened {
+                removed: 2,
+                failure: Failure {
+                    violation: Violation::MonotonicityFlip,
+                    index: 2,
+                    pair: (3, 2),
+                },
+            }
+        );
+        assert_eq!(
+            rules.dampened_verdict(&[8, 6, 4, 4, 1]),
+            ReportVerdict::Dampened {
+                removed: 3,
+                failure: Failure {
+                    violation: Violation::StepOutOfRange,
+                    index: 3,
+                    pair: (4, 4),
+                },
+            }
+        );
+        assert_eq!(
+            rules.dampened_verdict(&[5, 1, 2, 3, 4, 5]),
+            ReportVerdict::Dampened {
+                removed: 0,
+                failure: Failure {
+                    violation: Violation::StepOutOfRange,
+                    index: 1,
+                    pair: (5, 1),
+                },
+            }
+        );
+        assert!(!rules.dampened_verdict(&[1, 2, 7, 8, 9]).is_safe());
+        assert!(!rules.dampened_verdict(&[9, 7, 6, 2, 1]).is_safe());
+    }
+
+    fn levels(line: &str) -> Vec<i64> {
+        line.split(" ").map(|s| s.parse().unwrap()).collect()
+    }
+
+    fn brute_force_removals(rules: &SafetyRules, levels: &[i64]) -> usize {
+        (0u32..1 << levels.len())
+            .filter(|removed| {
+                let kept = levels
+                    .iter()
+                    .copied()
+                    .enumerate()
+                    .filter(|(index, _)| removed & (1 << index) == 0);
+                rules.subset_is_good_until(kept).is_none()
+            })
+            .map(|removed| removed.count_ones() as usize)
+            .min()
+            .unwrap()
+    }
+
+    /// Tries every set of overwritten levels, checking whether values can be found for them by
+    /// narrowing the range each level can take from left to right.
+    fn brute_force_overwrites(rules: &SafetyRules, levels: &[i64]) -> Option<usize> {
+        let (min, max) = rules.step_bounds();
+        if min > max {
+            // no step is allowed at all
+            return (levels.len() <= 1).then_some(0);
+        }
+        let directions: &[i128] = match rules.trend {
+            Trend::Increasing => &[1],
+            Trend::Decreasing => &[-1],
+            Trend::Either => &[-1, 1],
+        };
+
+        (0u32..1 << levels.len())
+            .filter(|overwritten| {
+                directions.iter().any(|&direction| {
+                    let mut range: Option<(i128, i128)> = None;
+                    levels.iter().enumerate().all(|(index, &level)| {
+                        let (mut low, mut high) = (i64::MIN as i128, i64::MAX as i128);
+                        if overwritten & (1 << index) == 0 {
+                            (low, high) = (level as i128, level as i128);
+                        }
+                        if let Some((previous_low, previous_high)) = range {
+                            let (min_step, max_step) = if direction > 0 {
+                                (min, max)
+                            } else {
+                                (-max, -min)
+                            };
+                            low = low.max(previous_low + min_step);
+                            high = high.min(previous_high + max_step);
+                        }
+                        range = Some((low, high));
+                        low <= high
+                    })
+                })
+            })
+            .map(|overwritten| overwritten.count_ones() as usize)
+            .min()
+    }
+
+    #[test]
+    fn removals_test() {
+        let rules = SafetyRules::default();
+        assert_eq!(rules.minimal_removals(&[7, 6, 4, 2, 1]), []);
+        assert_eq!(rules.minimal_removals(&[1, 3, 2, 4, 5]).len(), 1);
+        assert_eq!(rules.minimal_removals(&[1, 2, 7, 8, 9]).len(), 2);
+        assert_eq!(rules.minimal_removals(&[9, 7, 6, 2, 1]).len(), 2);
+        assert_eq!(rules.minimal_removals(&[]), []);
+        assert_eq!(rules.minimal_removals(&[4]), []);
+        assert_eq!(rules.minimal_removals(&[4, 4]).len(), 1);
+
+        assert_eq!(
+            rules.is_safe_with_removals(&[8, 6, 4, 4, 1], 1),
+            Some(vec![2])
+        );
+        assert_eq!(rules.is_safe_with_removals(&[1, 2, 7, 8, 9], 1), None);
+        assert_eq!(
+            rules
+                .is_safe_with_removals(&[1, 2, 7, 8, 9], 2)
+                .map(|r| r.len()),
+            Some(2)
+        );
+        assert_eq!(rules.is_safe_with_removals(&[1, 2, 7, 8, 9], 0), None);
+        assert_eq!(rules.is_safe_with_removals(&[1, 2, 3], 0), Some(vec![]));
+    }
+
+    #[test]
+    fn removals_part2_test() {
+        let rules = SafetyRules::default();
+        let input = "7 6 4 2 1
+1 2 7 8 9
+9 7 6 2 1
+1 3 2 4 5
+8 6 4 4 1
+1 3 6 7 9
+48 46 47 49 51 54 56
+1 1 2 3 4 5
+1 2 3 4 5 5
+5 1 2 3 4 5
+1 4 3 2 1
+1 6 7 8 9
+1 2 3 4 3
+9 8 7 6 7
+7 10 8 10 11
+29 28 27 25 26 25 22 20";
+
+        let safe = input
+            .lines()
+            .filter(|line| rules.is_safe_with_removals(&levels(line), 1).is_some())
+            .count();
+        assert_eq!(part2(input), Ok(safe as u32));
+    }
+
+    #[test]
+    fn safety_rules_test() {
+        let increasing = SafetyRules {
+            trend: Trend::Increasing,
+            ..SafetyRules::default()
+        };
+        assert_eq!(increasing.verdict(&[1, 3, 6, 7, 9]), ReportVerdict::Safe);
+        assert_eq!(
+            increasing.verdict(&[7, 6, 4, 2, 1]),
+            ReportVerdict::Unsafe(Failure {
+                violation: Violation::WrongDirection,
+                index: 1,
+                pair: (7, 6),
+            })
+        );
+        assert_eq!(
+            increasing.verdict(&[1, 3, 2, 4, 5]),
+            ReportVerdict::Unsafe(Failure {
+                violation: Violation::WrongDirection,
+                index: 2,
+                pair: (3, 2),
+            })
+        );
+        assert_eq!(
+            increasing.dampened_verdict(&[9, 1, 2, 3]),
+            ReportVerdict::Dampened {
+                removed: 0,
+                failure: Failure {
+                    violation: Violation::StepOutOfRange,
+                    index: 1,
+                    pair: (9, 1),
+                },
+            }
+        );
+
+        let non_strict = SafetyRules {
+            min_step: 0,
+            strict: false,
+            ..SafetyRules::default()
+        };
+        assert_eq!(non_strict.verdict(&[8, 8, 6, 6, 3]), ReportVerdict::Safe);
+        assert_eq!(non_strict.verdict(&[1, 1, 1]), ReportVerdict::Safe);
+        assert_eq!(
+            non_strict.verdict(&[1, 2, 2, 1]),
+            ReportVerdict::Unsafe(Failure {
+                violation: Violation::MonotonicityFlip,
+                index: 3,
+                pair: (2, 1),
+            })
+        );
+
+        let strict_zero = SafetyRules {
+            min_step: 0,
+            ..SafetyRules::default()
+        };
+        assert_eq!(
+            strict_zero.verdict(&[8, 8, 6]),
+            ReportVerdict::Unsafe(Failure {
+                violation: Violation::Unchanged,
+                index: 1,
+                pair: (8, 8),
+            })
+        );
+
+        let wide = SafetyRules {
+            min_step: 2,
+            max_step: 5,
+            trend: Trend::Decreasing,
+            ..SafetyRules::default()
+        };
+        let input = "7 5 4 2
+1 2 7 8 9
+9 7 2 0 -2
+20 15 11 9 4
+1 3 6 7 9";
+        assert_eq!(wide.count_safe(input), Ok(2));
+        assert_eq!(wide.count_dampened_safe(input), Ok(3));
+        assert_eq!(SafetyRules::default().count_safe(input), part1(input));
+    }
+
+    fn safety_rules() -> impl Strategy<Value = SafetyRules> {
+        let trend = prop_oneof![
+            Just(Trend::Increasing),
+            Just(Trend::Decreasing),
+            Just(Trend::Either)
+        ];
+        (0..3u64, 0..4u64, any::<bool>(), trend).prop_map(|(min_step, extra, strict, trend)| {
+            SafetyRules {
+                min_step,
+                max_step: min_step + extra,
+                strict,
+                trend,
+            }
+        })
+    }
+
+    #[test]
+    fn repair_test() {
+        let rules = SafetyRules::default();
+        assert_eq!(
+            rules.repair_by_overwrite(&[1, 2, 7, 8, 9]),
+            Some(Repair {
+                changed: vec![1],
+                levels: vec![1, 4, 7, 8, 9],
+            })
+        );
+        assert_eq!(
+            rules.repair_by_overwrite(&[9, 7, 6, 2, 1]),
+            Some(Repair {
+                changed: vec![2],
+                levels: vec![9, 7, 4, 2, 1],
+            })
+        );
+        assert_eq!(
+            rules.repair_by_overwrite(&[7, 6, 4, 2, 1]),
+            Some(Repair {
+                changed: vec![],
+                levels: vec![7, 6, 4, 2, 1],
+            })
+        );
+        assert_eq!(
+            rules
+                .repair_by_overwrite(&[8, 8, 8])
+                .map(|repair| repair.changed.len()),
+            Some(2)
+        );
+
+        assert_eq!(
+            rules.repair_by_deletion(&[1, 3, 2, 4, 5]),
+            Repair {
+                changed: vec![1],
+                levels: vec![1, 2, 4, 5],
+            }
+        );
+        assert_eq!(rules.repair_by_deletion(&[1, 2, 7, 8, 9]).changed.len(), 2);
+
+        let impossible = SafetyRules {
+            min_step: 4,
+            max_step: 3,
+            ..SafetyRules::default()
+        };
+        assert_eq!(impossible.repair_by_overwrite(&[1, 5]), None);
+        assert_eq!(
+            impossible.repair_by_overwrite(&[1]),
+            Some(Repair {
+                changed: vec![],
+                levels: vec![1],
+            })
+        );
+    }
+
+    #[test]
+    fn overwrite_extremes_test() {
+        let increasing = SafetyRules {
+            trend: Trend::Increasing,
+            ..SafetyRules::default()
+        };
+        assert_eq!(
+            increasing.repair_by_overwrite(&[0, i64::MIN]),
+            Some(Repair {
+                changed: vec![1],
+                levels: vec![0, 1],
+            })
+        );
+        assert_eq!(
+            increasing.repair_by_overwrite(&[i64::MIN, i64::MIN]),
+            Some(Repair {
+                changed: vec![1],
+                levels: vec![i64::MIN, i64::MIN + 1],
+            })
+        );
+        assert_eq!(
+            increasing.repair_by_overwrite(&[i64::MAX, i64::MAX]),
+            Some(Repair {
+                changed: vec![0],
+                levels: vec![i64::MAX - 1, i64::MAX],
+            })
+        );
+
+        let decreasing = SafetyRules {
+            trend: Trend::Decreasing,
+            ..SafetyRules::default()
+        };
+        assert_eq!(
+            decreasing.repair_by_overwrite(&[i64::MIN, i64::MIN]),
+            Some(Repair {
+                changed: vec![0],
+                levels: vec![i64::MIN + 1, i64::MIN],
+            })
+        );
+
+        // two steps of 2^63 - 1 just fit in the range, three don't
+        let huge = SafetyRules {
+            min_step: (1 << 63) - 1,
+            max_step: u64::MAX,
+            ..increasing
+        };
+        assert_eq!(
+            huge.repair_by_overwrite(&[0, 0, 0]),
+            Some(Repair {
+                changed: vec![0, 2],
+                levels: vec![i64::MIN + 1, 0, i64::MAX],
+            })
+        );
+        assert_eq!(huge.repair_by_overwrite(&[0, 0, 0, 0]), None);
+    }
+
+    proptest! {
+        #[test]
+        fn repairs_are_safe(
+            rules in safety_rules(),
+            levels in prop::collection::vec(-20..20i64, 0..12)
+        ) {
+            let deletion = rules.repair_by_deletion(&levels);
+            prop_assert_eq!(rules.verdict(&deletion.levels), ReportVerdict::Safe);
+            prop_assert_eq!(deletion.changed.len() + deletion.levels.len(), levels.len());
+
+            if let Some(overwrite) = rules.repair_by_overwrite(&levels) {
+                prop_assert_eq!(rules.verdict(&overwrite.levels), ReportVerdict::Safe);
+                prop_assert_eq!(overwrite.levels.len(), levels.len());
+                for (index, (&before, &after)) in levels.iter().zip(&overwrite.levels).enumerate() {
+                    prop_assert_eq!(before != after, overwrite.changed.contains(&index));
+                }
+                if rules.verdict(&levels).is_safe() {
+                    prop_assert!(overwrite.changed.is_empty());
+                }
+            } else {
+                prop_assert!(levels.len() > 1);
+            }
+        }
+
+        #[test]
+        fn removals_match_brute_force(
+            rules in safety_rules(),
+            levels in prop::collection::vec(0..12i64, 0..10)
+        ) {
+            let removals = rules.minimal_removals(&levels);
+            prop_assert_eq!(removals.len(), brute_force_removals(&rules, &levels));
+
+            let kept = levels
+                .iter()
+                .copied()
+                .enumerate()
+                .filter(|(index, _)| !removals.contains(index));
+            prop_assert_eq!(rules.subset_is_good_until(kept), None);
+        }
+
+        #[test]
+        fn overwrites_match_brute_force(
+            rules in safety_rules(),
+            levels in prop::collection::vec(
+                prop_oneof![-4..4i64, i64::MIN..i64::MIN + 4, i64::MAX - 3..=i64::MAX],
+                0..8
+            )
+        ) {
+            let overwrite = rules.repair_by_overwrite(&levels);
+            prop_assert_eq!(
+                overwrite.as_ref().map(|repair| repair.changed.len()),
+                brute_force_overwrites(&rules, &levels)
+            );
+            if let Some(overwrite) = overwrite {
+                prop_assert_eq!(rules.verdict(&overwrite.levels), ReportVerdict::Safe);
+            }
+        }
+
+        #[test]
+        fn one_removal_matches_dampener(
+            rules in safety_rules(),
+            levels in prop::collection::vec(0..12i64, 1..10)
+        ) {
+            prop_assert_eq!(
+                rules.is_safe_with_removals(&levels, 1).is_some(),
+                rules.dampened_verdict(&levels).is_safe()
+            );
+            if let Some(&[removed]) = rules.is_safe_with_removals(&levels, 1).as_deref() {
+                prop_assert_eq!(rules.subset_is_good_until(without(&levels, removed)), None);
+            }
+        }
+    }
+
+    #[test]
+    fn long_report_test() {
+        let increasing: Vec<String> = (0..1000).map(|level: i64| level.to_string()).collect();
+        let mut dampened = increasing.clone();
+        dampened[500] = "10000".to_string();
+        let mut broken = dampened.clone();
+        broken[700] = "-5".to_string();
+
+        let input = format!(
+            "{}\n{}\n{}\n",
+            increasing.join(" "),
+            dampened.join(" "),
+            broken.join(" ")
+        );
+        assert_eq!(part1(&input), Ok(1));
+        assert_eq!(part2(&input), Ok(2));
+
+        let levels = Levels::parse(1, &dampened.join(" ")).unwrap();
+        let expected: Vec<i64> = dampened
+            .iter()
+            .map(|level| level.parse().unwrap())
+            .collect();
+        assert_eq!(levels, Levels::Heap(expected));
+        assert_eq!(
+            SafetyRules::default().dampened_verdict(&levels),
+            ReportVerdict::Dampened {
+                removed: 500,
+                failure: Failure {
+                    violation: Violation::StepOutOfRange,
+                    index: 500,
+                    pair: (499, 10000),
+                },
+            }
+        );
+
+        assert_eq!(
+            Levels::parse(1, "7 6 4 2 1"),
+            Ok(Levels::Inline([7, 6, 4, 2, 1].into_iter().collect()))
+        );
+    }
+
+    #[test]
+    fn wide_levels_test() {
+        let input = "9000000000 9000000002 9000000005\n-9223372036854775808 9223372036854775807\n";
+        assert_eq!(part1(input), Ok(1));
+        assert_eq!(part2(input), Ok(2));
+    }
+
+    #[test]
+    fn parse_error_test() {
+        let input = "7 6 4 2 1\r\n\r\n1 2 7 x8 9\n";
+        assert_eq!(
+            part1(input),
+            Err(ParseError {
+                line: 3,
+                column: 7,
+                token: "x8".to_string(),
+            })
+        );
+        assert_eq!(part2(input), part1(input));
+        assert_eq!(
+            part1(input).unwrap_err().to_string(),
+            "invalid level at line 3, column 7: \"x8\""
+        );
+
+        // blank lines and CRLF are not reports
+        assert_eq!(part1("7 6 4 2 1\r\n\r\n1 3 6 7 9\r\n"), Ok(2));
     }
 }
@@ -1,7 +1,20 @@
 use aoc_runner_derive::aoc;
 use nom::{
-    branch::alt, bytes::complete::tag, character::complete::anychar, combinator::map,
-    multi::many_till, sequence::separated_pair, IResult, Parser,
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::{anychar, digit1, multispace0, one_of},
+    combinator::{consumed, map, opt},
+    error::ErrorKind,
+    multi::many_till,
+    sequence::separated_pair,
+    IResult, Parser,
+};
+use std::{
+    error::Error,
+    fmt::{Display, Write},
+    io,
+    io::Read,
+    sync::LazyLock,
 };
 
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
@@ -9,97 +22,858 @@ pub enum Instr {
     Enable,
     Disable,
     Mul(i32, i32),
+    Add(i32, i32),
+    Sub(i32, i32),
+    Neg(i32),
+    Toggle,
 }
 
 impl Instr {
-    pub fn product(&self) -> i32 {
+    /// The exact product of a `mul`, or zero for any other instruction.
+    pub fn product(&self) -> i64 {
         match self {
-            Instr::Mul(a, b) => a * b,
+            Instr::Mul(a, b) => i64::from(*a) * i64::from(*b),
             _ => 0,
         }
     }
 }
 
-fn parse_mul(input: &str) -> IResult<&str, Instr> {
-    let (input, _) = tag("mul(")(input)?;
-    let (input, (a, b)) = separated_pair(
-        nom::character::complete::i32,
-        tag(","),
-        nom::character::complete::i32,
-    )
-    .parse(input)?;
-    let (input, _) = tag(")")(input)?;
+impl Display for Instr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Instr::Enable => write!(f, "do()"),
+            Instr::Disable => write!(f, "don't()"),
+            Instr::Mul(a, b) => write!(f, "mul({a},{b})"),
+            Instr::Add(a, b) => write!(f, "add({a},{b})"),
+            Instr::Sub(a, b) => write!(f, "sub({a},{b})"),
+            Instr::Neg(a) => write!(f, "neg({a})"),
+            Instr::Toggle => write!(f, "toggle()"),
+        }
+    }
+}
+
+/// Which spellings of instruction operands are accepted.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Dialect {
+    /// The most digits an operand may have, not counting its sign.
+    pub max_digits: Option<usize>,
+    /// Whether operands may start with `+` or `-`.
+    pub signed: bool,
+    /// Whether whitespace may surround operands inside the parentheses.
+    pub whitespace: bool,
+}
+
+impl Dialect {
+    /// Unsigned operands of one to three digits, as in the puzzle statement.
+    pub const STRICT: Dialect = Dialect {
+        max_digits: Some(3),
+        signed: false,
+        whitespace: false,
+    };
+
+    /// Any `i32` operand.
+    pub const LENIENT: Dialect = Dialect {
+        max_digits: None,
+        signed: true,
+        whitespace: false,
+    };
+
+    fn space<'a>(&self, input: &'a str) -> IResult<&'a str, ()> {
+        if self.whitespace {
+            map(multispace0, drop).parse(input)
+        } else {
+            Ok((input, ()))
+        }
+    }
+
+    fn operand<'a>(&self, input: &'a str) -> IResult<&'a str, i32> {
+        let (input, _) = self.space(input)?;
+        let (digits, negative) = if self.signed {
+            map(opt(one_of("+-")), |sign| sign == Some('-')).parse(input)?
+        } else {
+            (input, false)
+        };
+        let (rest, digits) = digit1(digits)?;
+
+        let error = || nom::Err::Error(nom::error::Error::new(input, ErrorKind::Digit));
+        if self.max_digits.is_some_and(|max| digits.len() > max) {
+            return Err(error());
+        }
+        let magnitude: i64 = digits.parse().map_err(|_| error())?;
+        let value =
+            i32::try_from(if negative { -magnitude } else { magnitude }).map_err(|_| error())?;
+
+        let (rest, _) = self.space(rest)?;
+        Ok((rest, value))
+    }
+
+    /// Parses the `(a,b)` after a binary instruction's name.
+    fn pair<'a>(&self, input: &'a str) -> IResult<&'a str, (i32, i32)> {
+        let (input, _) = tag("(")(input)?;
+        let (input, pair) = separated_pair(
+            |input| self.operand(input),
+            tag(","),
+            |input| self.operand(input),
+        )
+        .parse(input)?;
+        let (input, _) = tag(")")(input)?;
+
+        Ok((input, pair))
+    }
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Dialect::LENIENT
+    }
+}
+
+fn parse_mul<'a>(dialect: &Dialect, input: &'a str) -> IResult<&'a str, Instr> {
+    let (input, _) = tag("mul")(input)?;
+    let (input, (a, b)) = dialect.pair(input)?;
 
     Ok((input, Instr::Mul(a, b)))
 }
 
-fn parse_dont(input: &str) -> IResult<&str, Instr> {
+fn parse_dont<'a>(_: &Dialect, input: &'a str) -> IResult<&'a str, Instr> {
     let (input, _) = tag("don't()")(input)?;
     Ok((input, Instr::Disable))
 }
 
-fn parse_do(input: &str) -> IResult<&str, Instr> {
+fn parse_do<'a>(_: &Dialect, input: &'a str) -> IResult<&'a str, Instr> {
     let (input, _) = tag("do()")(input)?;
     Ok((input, Instr::Enable))
 }
 
+fn parse_add<'a>(dialect: &Dialect, input: &'a str) -> IResult<&'a str, Instr> {
+    let (input, _) = tag("add")(input)?;
+    let (input, (a, b)) = dialect.pair(input)?;
+
+    Ok((input, Instr::Add(a, b)))
+}
+
+fn parse_sub<'a>(dialect: &Dialect, input: &'a str) -> IResult<&'a str, Instr> {
+    let (input, _) = tag("sub")(input)?;
+    let (input, (a, b)) = dialect.pair(input)?;
+
+    Ok((input, Instr::Sub(a, b)))
+}
+
+fn parse_neg<'a>(dialect: &Dialect, input: &'a str) -> IResult<&'a str, Instr> {
+    let (input, _) = tag("neg(")(input)?;
+    let (input, a) = dialect.operand(input)?;
+    let (input, _) = tag(")")(input)?;
+
+    Ok((input, Instr::Neg(a)))
+}
+
+fn parse_toggle<'a>(_: &Dialect, input: &'a str) -> IResult<&'a str, Instr> {
+    let (input, _) = tag("toggle()")(input)?;
+    Ok((input, Instr::Toggle))
+}
+
 pub fn parse_instr(input: &str) -> IResult<&str, Instr> {
-    let (input, instr) = alt((parse_mul, parse_do, parse_dont)).parse(input)?;
+    let (input, instr) = alt((
+        |input| parse_mul(&Dialect::LENIENT, input),
+        |input| parse_do(&Dialect::LENIENT, input),
+        |input| parse_dont(&Dialect::LENIENT, input),
+    ))
+    .parse(input)?;
 
     Ok((input, instr))
 }
 
+/// Skips over corrupted memory up to the next instruction and parses it, only trying the
+/// parsers where an `m` or a `d` could start an instruction.
 pub fn parse_until_next_instr(input: &str) -> IResult<&str, Instr> {
+    let mut offset = 0;
+    while let Some(found) = memchr::memchr2(b'm', b'd', &input.as_bytes()[offset..]) {
+        let start = offset + found;
+        if let Ok(parsed) = parse_instr(&input[start..]) {
+            return Ok(parsed);
+        }
+        offset = start + 1;
+    }
+
+    Err(nom::Err::Error(nom::error::Error::new(
+        &input[input.len()..],
+        nom::error::ErrorKind::Eof,
+    )))
+}
+
+/// Like `parse_until_next_instr`, but trying the parsers at every character.
+pub fn parse_until_next_instr_many_till(input: &str) -> IResult<&str, Instr> {
     let (input, (_, mul)) = many_till(map(anychar, drop), parse_instr).parse(input)?;
     Ok((input, mul))
 }
 
-#[aoc(day3, part1)]
-pub fn part1(mut input: &str) -> i32 {
-    let mut totals = 0;
-    while input.len() != 0 {
-        let Ok((new_input, mul)) = parse_until_next_instr(input) else {
-            break;
-        };
+/// How the machine adds values to its accumulator.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum Arithmetic {
+    /// Exact, using the full range of the accumulator.
+    #[default]
+    Wide,
+    /// Stops accumulating once the total leaves the range of an `i64`.
+    Checked,
+    /// Clamps the total to the range of an `i64`.
+    Saturating,
+}
+
+/// The state of the machine running the instructions found in corrupted memory.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Machine {
+    pub accumulator: i128,
+    pub arithmetic: Arithmetic,
+    /// Whether an arithmetic instruction overflowed a `Checked` accumulator.
+    pub overflowed: bool,
+    /// Whether arithmetic instructions currently have any effect.
+    pub enabled: bool,
+    /// Arithmetic instructions that were applied to the accumulator.
+    pub executed: usize,
+    /// Arithmetic instructions that were ignored because the machine was disabled.
+    pub skipped: usize,
+}
 
-        totals += mul.product();
-        input = new_input;
+impl Default for Machine {
+    fn default() -> Self {
+        Machine::new(Arithmetic::default())
     }
-    totals
 }
 
-#[aoc(day3, part2)]
-pub fn part2(mut input: &str) -> i32 {
-    let mut totals = 0;
-    let mut enabled_flag = true;
-    while input.len() != 0 {
-        let Ok((new_input, instr)) = parse_until_next_instr(input) else {
-            break;
+impl Machine {
+    pub fn new(arithmetic: Arithmetic) -> Self {
+        Machine {
+            accumulator: 0,
+            arithmetic,
+            overflowed: false,
+            enabled: true,
+            executed: 0,
+            skipped: 0,
+        }
+    }
+
+    /// Adds `value` to the accumulator if the machine is enabled.
+    #[inline(always)]
+    pub fn accumulate(&mut self, value: i64) {
+        if !self.enabled {
+            self.skipped += 1;
+            return;
+        }
+
+        let total = self.accumulator + i128::from(value);
+        self.accumulator = match self.arithmetic {
+            Arithmetic::Wide => total,
+            Arithmetic::Checked if i64::try_from(total).is_err() => {
+                self.overflowed = true;
+                return;
+            }
+            Arithmetic::Checked => total,
+            Arithmetic::Saturating => total.clamp(i64::MIN.into(), i64::MAX.into()),
         };
+        self.executed += 1;
+    }
+}
 
-        match instr {
-            Instr::Enable => enabled_flag = true,
-            Instr::Disable => enabled_flag = false,
-            Instr::Mul(a, b) => {
-                if enabled_flag {
-                    totals += a * b;
+/// An arithmetic instruction took a `Checked` total out of range.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct OverflowError {
+    pub instr: Instr,
+    pub span: Span,
+}
+
+impl Display for OverflowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "total overflowed at {} (bytes {}..{})",
+            self.instr, self.span.start, self.span.end
+        )
+    }
+}
+
+impl Error for OverflowError {}
+
+/// An instruction the machine understands: how to find it in memory, and what it does.
+#[derive(Clone, Copy)]
+pub struct Opcode {
+    /// What the instruction starts with. Its parser is only tried where memory starts with the
+    /// first byte of the name.
+    pub name: &'static str,
+    pub parse: for<'a> fn(&Dialect, &'a str) -> IResult<&'a str, Instr>,
+    pub execute: fn(&mut Machine, Instr),
+}
+
+impl Opcode {
+    pub const MUL: Opcode = Opcode {
+        name: "mul",
+        parse: parse_mul,
+        execute: |machine, instr| machine.accumulate(instr.product()),
+    };
+
+    pub const DO: Opcode = Opcode {
+        name: "do",
+        parse: parse_do,
+        execute: |machine, _| machine.enabled = true,
+    };
+
+    pub const DONT: Opcode = Opcode {
+        name: "don't",
+        parse: parse_dont,
+        execute: |machine, _| machine.enabled = false,
+    };
+
+    pub const ADD: Opcode = Opcode {
+        name: "add",
+        parse: parse_add,
+        execute: |machine, instr| {
+            if let Instr::Add(a, b) = instr {
+                machine.accumulate(i64::from(a) + i64::from(b))
+            }
+        },
+    };
+
+    pub const SUB: Opcode = Opcode {
+        name: "sub",
+        parse: parse_sub,
+        execute: |machine, instr| {
+            if let Instr::Sub(a, b) = instr {
+                machine.accumulate(i64::from(a) - i64::from(b))
+            }
+        },
+    };
+
+    pub const NEG: Opcode = Opcode {
+        name: "neg",
+        parse: parse_neg,
+        execute: |machine, instr| {
+            if let Instr::Neg(a) = instr {
+                machine.accumulate(-i64::from(a))
+            }
+        },
+    };
+
+    /// Flips whether the machine is enabled.
+    pub const TOGGLE: Opcode = Opcode {
+        name: "toggle",
+        parse: parse_toggle,
+        execute: |machine, _| machine.enabled = !machine.enabled,
+    };
+}
+
+/// The opcodes a machine recognises. When several could parse at the same position,
+/// the one registered first wins.
+#[derive(Clone, Default)]
+pub struct InstructionSet {
+    opcodes: Vec<Opcode>,
+    /// The distinct first bytes of the opcode names.
+    starts: Vec<u8>,
+    dialect: Dialect,
+}
+
+impl InstructionSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only `mul`, as in part 1.
+    pub fn part1() -> Self {
+        Self::new().register(Opcode::MUL)
+    }
+
+    /// `mul`, `do` and `don't`, as in part 2.
+    pub fn part2() -> Self {
+        Self::part1().register(Opcode::DO).register(Opcode::DONT)
+    }
+
+    pub fn register(mut self, opcode: Opcode) -> Self {
+        let start = *opcode
+            .name
+            .as_bytes()
+            .first()
+            .expect("opcode name is empty");
+        if !self.starts.contains(&start) {
+            self.starts.push(start);
+        }
+        self.opcodes.push(opcode);
+        self
+    }
+
+    pub fn opcodes(&self) -> &[Opcode] {
+        &self.opcodes
+    }
+
+    /// Parses operands according to `dialect`.
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+
+    /// Parses an instruction at the very start of `input`.
+    pub fn parse_instr<'a>(&self, input: &'a str) -> IResult<&'a str, (Instr, &Opcode)> {
+        for opcode in &self.opcodes {
+            if let Ok((rest, instr)) = (opcode.parse)(&self.dialect, input) {
+                return Ok((rest, (instr, opcode)));
+            }
+        }
+
+        Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            nom::error::ErrorKind::Alt,
+        )))
+    }
+
+    /// Offset of the next byte in `haystack` that could start an instruction.
+    fn next_candidate(&self, haystack: &[u8]) -> Option<usize> {
+        match *self.starts.as_slice() {
+            [] => None,
+            [a] => memchr::memchr(a, haystack),
+            [a, b] => memchr::memchr2(a, b, haystack),
+            [a, b, c] => memchr::memchr3(a, b, c, haystack),
+            _ => haystack.iter().position(|byte| self.starts.contains(byte)),
+        }
+    }
+
+    /// Every instruction in `input`, in order.
+    pub fn instructions<'s, 'a>(&'s self, input: &'a str) -> Instructions<'s, 'a> {
+        Instructions {
+            set: self,
+            input,
+            offset: 0,
+        }
+    }
+
+    /// Every instruction in the corrupted memory read from `reader`, in order.
+    pub fn instructions_from_reader<R: Read>(&self, reader: R) -> ReadInstructions<'_, R> {
+        ReadInstructions {
+            set: self,
+            reader,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            buffer: Vec::new(),
+            base: 0,
+            cursor: 0,
+            eof: false,
+        }
+    }
+
+    /// Runs every instruction read from `reader` on `machine`. An overflow is reported as an
+    /// `io::Error` wrapping the `OverflowError`.
+    pub fn run_reader(&self, machine: &mut Machine, reader: impl Read) -> io::Result<()> {
+        let mut instructions = self.instructions_from_reader(reader);
+        while let Some(found) = instructions.next_opcode() {
+            let (span, instr, opcode) = found?;
+            (opcode.execute)(machine, instr);
+            if machine.overflowed {
+                return Err(io::Error::other(OverflowError { instr, span }));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Runs every instruction in `input` on `machine`, stopping at the first one that overflows
+    /// a `Checked` machine.
+    pub fn run(&self, machine: &mut Machine, input: &str) -> Result<(), OverflowError> {
+        let mut instructions = self.instructions(input);
+        while let Some((span, instr, opcode)) = instructions.next_opcode() {
+            (opcode.execute)(machine, instr);
+            if machine.overflowed {
+                return Err(OverflowError { instr, span });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes `input` with every instruction marked up, running them on a fresh machine to tell
+    /// which arithmetic instructions were skipped because the machine was disabled.
+    pub fn highlight(&self, input: &str, style: Highlight, f: &mut impl Write) -> std::fmt::Result {
+        let mut machine = Machine::default();
+        let mut instructions = self.instructions(input);
+        let mut written = 0;
+        while let Some((span, instr, opcode)) = instructions.next_opcode() {
+            f.write_str(&input[written..span.start])?;
+
+            let (executed, skipped) = (machine.executed, machine.skipped);
+            (opcode.execute)(&mut machine, instr);
+            let text = &input[span.start..span.end];
+            match style {
+                Highlight::Ansi if machine.skipped > skipped => write!(f, "\x1b[2m{text}\x1b[0m")?,
+                Highlight::Ansi if machine.executed > executed => {
+                    write!(f, "\x1b[1;32m{text}\x1b[0m")?
                 }
+                Highlight::Ansi => write!(f, "\x1b[1;36m{text}\x1b[0m")?,
+                Highlight::Plain if machine.skipped > skipped => write!(f, "{{{text}}}")?,
+                Highlight::Plain => write!(f, "[{text}]")?,
             }
+            written = span.end;
         }
 
-        input = new_input;
+        f.write_str(&input[written..])
     }
+}
 
-    totals
+/// Byte offsets of an instruction within the corrupted memory.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Iterator over the instructions in corrupted memory and where they were found.
+pub struct Instructions<'s, 'a> {
+    set: &'s InstructionSet,
+    input: &'a str,
+    offset: usize,
+}
+
+impl<'s> Instructions<'s, '_> {
+    /// Like `next`, but also returns the opcode that parsed the instruction.
+    pub fn next_opcode(&mut self) -> Option<(Span, Instr, &'s Opcode)> {
+        let haystack = self.input.as_bytes();
+        while let Some(found) = self.set.next_candidate(&haystack[self.offset..]) {
+            let start = self.offset + found;
+            if let Ok((after, (instr, opcode))) = self.set.parse_instr(&self.input[start..]) {
+                let span = Span {
+                    start,
+                    end: self.input.len() - after.len(),
+                };
+                self.offset = span.end;
+                return Some((span, instr, opcode));
+            }
+
+            // Candidates are always the first byte of a character.
+            self.offset = start + 1;
+        }
+
+        self.offset = self.input.len();
+        None
+    }
+}
+
+impl Iterator for Instructions<'_, '_> {
+    type Item = (Span, Instr);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_opcode().map(|(span, instr, _)| (span, instr))
+    }
+}
+
+/// How many bytes an opcode's parser may look past the point where it reports giving up. An
+/// attempt that ends closer than this to the end of what has been read so far is retried once
+/// more of the stream is available.
+pub const LOOKAHEAD: usize = 32;
+
+const DEFAULT_CHUNK_SIZE: usize = 1 << 13;
+
+/// Outcome of looking for an instruction at the scanner's cursor.
+enum Attempt<'s> {
+    Found(Instr, &'s Opcode, usize),
+    Miss,
+    Pending,
+}
+
+/// Iterator over the instructions in corrupted memory read from a stream, keeping only the
+/// unscanned tail of the stream in memory. Yields the same instructions as `Instructions`
+/// would on the whole stream, with spans relative to the start of the stream.
+pub struct ReadInstructions<'s, R> {
+    set: &'s InstructionSet,
+    reader: R,
+    chunk_size: usize,
+    buffer: Vec<u8>,
+    /// Offset of `buffer[0]` within the stream.
+    base: usize,
+    /// Where in `buffer` to look for the next instruction.
+    cursor: usize,
+    eof: bool,
+}
+
+impl<'s, R: Read> ReadInstructions<'s, R> {
+    /// Reads the stream `chunk_size` bytes at a time.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Like `next`, but also returns the opcode that parsed the instruction.
+    pub fn next_opcode(&mut self) -> Option<io::Result<(Span, Instr, &'s Opcode)>> {
+        loop {
+            let text = match std::str::from_utf8(&self.buffer) {
+                Ok(text) => text,
+                Err(e) if e.error_len().is_none() && !self.eof => {
+                    // A character split across chunks; scan up to it for now.
+                    std::str::from_utf8(&self.buffer[..e.valid_up_to()]).unwrap()
+                }
+                Err(e) => {
+                    return Some(Err(self.fail(io::Error::new(io::ErrorKind::InvalidData, e))))
+                }
+            };
+            let complete = self.eof && text.len() == self.buffer.len();
+
+            while self.cursor < text.len() {
+                match self.set.next_candidate(&text.as_bytes()[self.cursor..]) {
+                    Some(found) => self.cursor += found,
+                    None => {
+                        self.cursor = text.len();
+                        break;
+                    }
+                }
+
+                match self.attempt(&text[self.cursor..], complete) {
+                    Attempt::Found(instr, opcode, len) => {
+                        let span = Span {
+                            start: self.base + self.cursor,
+                            end: self.base + self.cursor + len,
+                        };
+                        self.cursor += len;
+                        return Some(Ok((span, instr, opcode)));
+                    }
+                    Attempt::Miss => self.cursor += 1,
+                    Attempt::Pending => break,
+                }
+            }
+
+            if complete {
+                return None;
+            }
+
+            self.buffer.drain(..self.cursor);
+            self.base += self.cursor;
+            self.cursor = 0;
+
+            let filled = self.buffer.len();
+            self.buffer.resize(filled + self.chunk_size, 0);
+            let read = loop {
+                match self.reader.read(&mut self.buffer[filled..]) {
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    result => break result,
+                }
+            };
+            match read {
+                Ok(read) => {
+                    self.buffer.truncate(filled + read);
+                    self.eof = read == 0;
+                }
+                Err(e) => return Some(Err(self.fail(e))),
+            }
+        }
+    }
+
+    /// Tries each opcode at the start of `rest`, unless one of them can't tell yet.
+    fn attempt(&self, rest: &str, complete: bool) -> Attempt<'s> {
+        let set = self.set;
+        for opcode in &set.opcodes {
+            let (left, instr) = match (opcode.parse)(&set.dialect, rest) {
+                Ok((left, instr)) => (left.len(), Some(instr)),
+                Err(nom::Err::Error(e) | nom::Err::Failure(e)) => (e.input.len(), None),
+                Err(nom::Err::Incomplete(_)) => (0, None),
+            };
+            if !complete && left < LOOKAHEAD {
+                return Attempt::Pending;
+            }
+            if let Some(instr) = instr {
+                return Attempt::Found(instr, opcode, rest.len() - left);
+            }
+        }
+
+        Attempt::Miss
+    }
+
+    /// Stops the iteration after reporting `error`.
+    fn fail(&mut self, error: io::Error) -> io::Error {
+        self.buffer.clear();
+        self.cursor = 0;
+        self.eof = true;
+        error
+    }
+}
+
+impl<R: Read> Iterator for ReadInstructions<'_, R> {
+    type Item = io::Result<(Span, Instr)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_opcode()
+            .map(|result| result.map(|(span, instr, _)| (span, instr)))
+    }
+}
+
+/// How `InstructionSet::highlight` marks up instructions.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Highlight {
+    /// Executed arithmetic in bold green, other instructions in bold cyan, and skipped arithmetic dimmed.
+    Ansi,
+    /// Instructions in `[brackets]`, except skipped arithmetic which is in `{braces}`.
+    Plain,
+}
+
+static PART1: LazyLock<InstructionSet> = LazyLock::new(InstructionSet::part1);
+static PART2: LazyLock<InstructionSet> = LazyLock::new(InstructionSet::part2);
+
+/// Every part 2 instruction in `input` with its span.
+pub fn instructions(input: &str) -> Instructions<'static, '_> {
+    PART2.instructions(input)
+}
+
+/// Every part 2 instruction read from `reader` with its span.
+pub fn instructions_from_reader<R: Read>(reader: R) -> ReadInstructions<'static, R> {
+    PART2.instructions_from_reader(reader)
+}
+
+/// `input` with the part 2 instructions marked up.
+pub fn highlight(input: &str, style: Highlight) -> String {
+    let mut highlighted = String::with_capacity(input.len());
+    // Writing to a String can't fail.
+    let _ = PART2.highlight(input, style, &mut highlighted);
+    highlighted
+}
+
+/// Corrupted memory along with the answers it should give.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Generated {
+    pub memory: String,
+    pub part1: i64,
+    pub part2: i64,
+}
+
+/// Instructions that don't quite parse. None of them can be completed by whatever follows.
+const DECOYS: [&str; 9] = [
+    "mul[3,7]",
+    "mul(32,64]",
+    "mul(4*",
+    "mul(6,9!",
+    "mul ( 2 , 4 )",
+    "?(12,34)",
+    "don't",
+    "do(",
+    "from()",
+];
+
+/// Memory of at least `size` bytes, the same for the same `seed`, mixing valid instructions
+/// with decoys and noise. Operands are kept within the strict dialect, so the answers hold for
+/// every dialect.
+pub fn generate(seed: u64, size: usize) -> Generated {
+    // splitmix64, which is fine with any seed
+    let mut state = seed;
+    let mut next = move |bound: u64| {
+        state = state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        (z ^ (z >> 31)) % bound
+    };
+
+    let mut generated = Generated {
+        memory: String::with_capacity(size + 16),
+        part1: 0,
+        part2: 0,
+    };
+    let mut enabled = true;
+    while generated.memory.len() < size {
+        let memory = &mut generated.memory;
+        match next(16) {
+            0..=3 => {
+                let (a, b) = (next(1000) as i64, next(1000) as i64);
+                // Writing to a String can't fail.
+                let _ = write!(memory, "mul({a},{b})");
+                generated.part1 += a * b;
+                if enabled {
+                    generated.part2 += a * b;
+                }
+            }
+            4 => {
+                memory.push_str("do()");
+                enabled = true;
+            }
+            5 => {
+                memory.push_str("don't()");
+                enabled = false;
+            }
+            6..=8 => memory.push_str(DECOYS[next(DECOYS.len() as u64) as usize]),
+            // `mul(5,5)` in `do_not_mul(5,5)` still counts, so this is only a decoy prefix
+            9 => memory.push_str("do_not_"),
+            _ => {
+                // anything but digits and punctuation that could finish a decoy
+                for _ in 0..=next(8) {
+                    let noise = match next(48) {
+                        0 => 'é',
+                        1 => ' ',
+                        2 => '\n',
+                        _ => char::from(b'!' + next(94) as u8),
+                    };
+                    if !matches!(noise, '0'..='9' | '(' | ')' | ',') {
+                        memory.push(noise);
+                    }
+                }
+            }
+        }
+    }
+
+    generated
+}
+
+/// Runs `set` on a `Checked` machine; its total always fits in an `i64`.
+fn checked_total(set: &InstructionSet, input: &str) -> Result<i64, OverflowError> {
+    let mut machine = Machine::new(Arithmetic::Checked);
+    set.run(&mut machine, input)?;
+    Ok(machine.accumulator as i64)
+}
+
+#[aoc(day3, part1)]
+pub fn part1(input: &str) -> Result<i64, OverflowError> {
+    checked_total(&PART1, input)
+}
+
+#[aoc(day3, part2)]
+pub fn part2(input: &str) -> Result<i64, OverflowError> {
+    checked_total(&PART2, input)
+}
+
+/// Part 2 trying every parser at every character, as a baseline for the candidate scanner.
+#[aoc(day3, part2, ManyTill)]
+pub fn part2_many_till(input: &str) -> Result<i64, OverflowError> {
+    let mut machine = Machine::new(Arithmetic::Checked);
+    let mut parser = many_till(map(anychar, drop), consumed(parse_instr));
+    let mut rest = input;
+    while let Ok((after, (_, (source, instr)))) = parser.parse(rest) {
+        match instr {
+            Instr::Enable => machine.enabled = true,
+            Instr::Disable => machine.enabled = false,
+            _ => machine.accumulate(instr.product()),
+        }
+        if machine.overflowed {
+            // The source can be spelled differently from `instr`, with a sign or leading zeros.
+            let end = input.len() - after.len();
+            let start = end - source.len();
+            return Err(OverflowError {
+                instr,
+                span: Span { start, end },
+            });
+        }
+        rest = after;
+    }
+
+    Ok(machine.accumulator as i64)
 }
 
 #[cfg(test)]
 mod test {
-    use crate::day3::{parse_mul, parse_until_next_instr, part1, part2, Instr};
+    use crate::day3::{
+        generate, highlight, instructions, instructions_from_reader, parse_instr, parse_mul,
+        parse_until_next_instr, parse_until_next_instr_many_till, part1, part2, part2_many_till,
+        Arithmetic, Dialect, Highlight, Instr, InstructionSet, Machine, Opcode, OverflowError,
+        Span,
+    };
+    use proptest::{
+        prelude::RngExt,
+        test_runner::{RngAlgorithm, TestRng},
+    };
 
     #[test]
     pub fn parses_mul() {
-        let (_, value) = parse_mul("mul(2,4)").unwrap();
+        let (_, value) = parse_mul(&Dialect::LENIENT, "mul(2,4)").unwrap();
         assert_eq!(value, Instr::Mul(2, 4));
     }
 
@@ -113,13 +887,407 @@ mod test {
     pub fn part1_test() {
         const INPUT: &str =
             "xmul(2,4)%&mul[3,7]!@^do_not_mul(5,5)+mul(32,64]then(mul(11,8)mul(8,5))";
-        assert_eq!(part1(INPUT), 161);
+        assert_eq!(part1(INPUT), Ok(161));
     }
 
     #[test]
     pub fn part2_test() {
         const INPUT: &str =
             "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
-        assert_eq!(part2(INPUT), 48);
+        assert_eq!(part2(INPUT), Ok(48));
+    }
+
+    #[test]
+    pub fn machine_test() {
+        const INPUT: &str =
+            "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
+
+        let mut machine = Machine::default();
+        InstructionSet::part2().run(&mut machine, INPUT).unwrap();
+        assert_eq!(
+            machine,
+            Machine {
+                accumulator: 48,
+                arithmetic: Arithmetic::Wide,
+                overflowed: false,
+                enabled: true,
+                executed: 2,
+                skipped: 2,
+            }
+        );
+
+        let mut machine = Machine::default();
+        InstructionSet::part1().run(&mut machine, INPUT).unwrap();
+        assert_eq!(machine.accumulator, 161);
+        assert_eq!(machine.executed, 4);
+    }
+
+    #[test]
+    pub fn extended_instruction_set_test() {
+        const INPUT: &str = "add(2,3)mul(2,4)xsub(10,4)neg(7)toggle()add(100,100)toggle()neg(1)";
+        let set = InstructionSet::part2()
+            .register(Opcode::ADD)
+            .register(Opcode::SUB)
+            .register(Opcode::NEG)
+            .register(Opcode::TOGGLE);
+
+        let mut machine = Machine::default();
+        set.run(&mut machine, INPUT).unwrap();
+        assert_eq!(machine.accumulator, 5 + 8 + 6 - 7 - 1);
+        assert_eq!(machine.executed, 5);
+        assert_eq!(machine.skipped, 1);
+
+        // opcodes that aren't registered are just corrupted memory
+        let mut machine = Machine::default();
+        InstructionSet::part1().run(&mut machine, INPUT).unwrap();
+        assert_eq!(machine.accumulator, 8);
+
+        // semantics can be swapped out for an existing grammar
+        let squares = InstructionSet::new().register(Opcode {
+            name: "mul",
+            execute: |machine, instr| {
+                if let Instr::Mul(a, b) = instr {
+                    machine.accumulate(i64::from(a * a + b * b))
+                }
+            },
+            ..Opcode::MUL
+        });
+        let mut machine = Machine::default();
+        squares.run(&mut machine, INPUT).unwrap();
+        assert_eq!(machine.accumulator, 20);
+    }
+
+    #[test]
+    pub fn instructions_test() {
+        const INPUT: &str =
+            "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
+        let found: Vec<_> = instructions(INPUT).collect();
+        assert_eq!(
+            found,
+            [
+                (Span { start: 1, end: 9 }, Instr::Mul(2, 4)),
+                (Span { start: 20, end: 27 }, Instr::Disable),
+                (Span { start: 28, end: 36 }, Instr::Mul(5, 5)),
+                (Span { start: 48, end: 57 }, Instr::Mul(11, 8)),
+                (Span { start: 59, end: 63 }, Instr::Enable),
+                (Span { start: 64, end: 72 }, Instr::Mul(8, 5)),
+            ]
+        );
+        for (span, _) in found {
+            assert!(parse_until_next_instr(&INPUT[span.start..span.end]).is_ok());
+        }
+
+        // spans are byte offsets, even around multi-byte characters
+        let found: Vec<_> = instructions("é→mul(1,2)").collect();
+        assert_eq!(found, [(Span { start: 5, end: 13 }, Instr::Mul(1, 2))]);
+        assert_eq!(instructions("").count(), 0);
+    }
+
+    #[test]
+    pub fn highlight_test() {
+        const INPUT: &str =
+            "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
+        assert_eq!(
+            highlight(INPUT, Highlight::Plain),
+            "x[mul(2,4)]&mul[3,7]!^[don't()]_{mul(5,5)}+mul(32,64]({mul(11,8)}un[do()]?[mul(8,5)])"
+        );
+        assert_eq!(
+            highlight("amul(1,1)don't()mul(2,2)", Highlight::Ansi),
+            "a\x1b[1;32mmul(1,1)\x1b[0m\x1b[1;36mdon't()\x1b[0m\x1b[2mmul(2,2)\x1b[0m"
+        );
+        assert_eq!(highlight("nothing here", Highlight::Ansi), "nothing here");
+    }
+
+    /// Hands out its data in pieces of the given sizes, then in one piece.
+    struct Pieces<'a> {
+        data: &'a [u8],
+        sizes: Vec<usize>,
+    }
+
+    impl std::io::Read for Pieces<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let size = if self.sizes.is_empty() {
+                self.data.len()
+            } else {
+                self.sizes.remove(0)
+            };
+            let n = size.min(buf.len()).min(self.data.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    pub fn reader_split_test() {
+        const INPUT: &str =
+            "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](é→mul(11,8)undo()?mul(8,5))do";
+        let expected: Vec<_> = instructions(INPUT).collect();
+        for split in 1..INPUT.len() {
+            let reader = Pieces {
+                data: INPUT.as_bytes(),
+                sizes: vec![split],
+            };
+            let found: Vec<_> = instructions_from_reader(reader)
+                .collect::<Result<_, _>>()
+                .unwrap();
+            assert_eq!(found, expected, "split at {split}");
+        }
+        for chunk_size in 1..=8 {
+            let found: Vec<_> = instructions_from_reader(INPUT.as_bytes())
+                .with_chunk_size(chunk_size)
+                .collect::<Result<_, _>>()
+                .unwrap();
+            assert_eq!(found, expected, "chunk size {chunk_size}");
+        }
+    }
+
+    #[test]
+    pub fn reader_long_input_test() {
+        let input = "mul(3,4)don't()mul(1,1)do()mul(000000000000000000000000000000000000000002,5)?"
+            .repeat(1000);
+        let mut machine = Machine::default();
+        InstructionSet::part2()
+            .run_reader(&mut machine, input.as_bytes())
+            .unwrap();
+        assert_eq!(machine.accumulator, 22 * 1000);
+        assert_eq!(part2(&input), Ok(22 * 1000));
+        let found: Vec<_> = instructions_from_reader(input.as_bytes())
+            .with_chunk_size(7)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(found, instructions(&input).collect::<Vec<_>>());
+    }
+
+    #[test]
+    pub fn reader_invalid_utf8_test() {
+        let mut found = instructions_from_reader(&b"mul(1,2)\xffmul(3,4)"[..]);
+        assert!(found.next().unwrap().is_err());
+        assert!(found.next().is_none());
+
+        // a truncated character at the end of the stream
+        let mut found = instructions_from_reader(&b"mul(1,2)\xc3"[..]);
+        assert!(found.next().unwrap().is_err());
+    }
+
+    #[test]
+    pub fn candidate_scanner_test() {
+        let mut rng = TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let mut next = move || rng.random::<u64>();
+        const PIECES: [&str; 10] = [
+            "mul(", "do()", "don't()", ",", ")", "m", "d", "ul", "é", "do",
+        ];
+        let input: String = (0..20_000)
+            .map(|_| match next() % 3 {
+                0 => PIECES[(next() % PIECES.len() as u64) as usize].to_string(),
+                1 => (next() % 1000).to_string(),
+                _ => "x".to_string(),
+            })
+            .collect();
+
+        assert_eq!(part2(&input), part2_many_till(&input));
+        assert!(part2(&input).is_ok());
+        assert!(instructions(&input).count() > 100);
+        let mut rest = input.as_str();
+        let mut naive = input.as_str();
+        while let Ok((after, instr)) = parse_until_next_instr(rest) {
+            let (naive_after, naive_instr) = parse_until_next_instr_many_till(naive).unwrap();
+            assert_eq!((after.len(), instr), (naive_after.len(), naive_instr));
+            (rest, naive) = (after, naive_after);
+        }
+        assert!(parse_until_next_instr_many_till(naive).is_err());
+
+        // more distinct first bytes than memchr3 handles
+        let set = InstructionSet::part2()
+            .register(Opcode::ADD)
+            .register(Opcode::SUB)
+            .register(Opcode::NEG)
+            .register(Opcode::TOGGLE);
+        let found: Vec<_> = set.instructions("xsub(1,2)toggle()nneg(3)").collect();
+        assert_eq!(
+            found,
+            [
+                (Span { start: 1, end: 9 }, Instr::Sub(1, 2)),
+                (Span { start: 9, end: 17 }, Instr::Toggle),
+                (Span { start: 18, end: 24 }, Instr::Neg(3)),
+            ]
+        );
+    }
+
+    #[test]
+    pub fn arithmetic_test() {
+        // these overflowed an i32
+        assert_eq!(part1("mul(2147483647,2)"), Ok(4294967294));
+        assert_eq!(part1("mul(-2147483648,-1)"), Ok(2147483648));
+        assert_eq!(
+            part1("mul(-2147483648,-2147483648)"),
+            Ok(4611686018427387904)
+        );
+        let set = InstructionSet::new()
+            .register(Opcode::ADD)
+            .register(Opcode::SUB)
+            .register(Opcode::NEG);
+        let mut machine = Machine::default();
+        set.run(
+            &mut machine,
+            "add(2147483647,1)sub(-2147483648,1)neg(-2147483648)",
+        )
+        .unwrap();
+        assert_eq!(machine.accumulator, 2147483648 - 2147483649 + 2147483648);
+
+        // operands outside an i32 are corrupted memory, not instructions
+        assert_eq!(part1("mul(2147483648,1)mul(3,3)"), Ok(9));
+
+        const BIG: &str = "mul(-2147483648,-2147483648)";
+        let input = format!("mul(1,1){BIG}{BIG}mul(1,1)");
+        assert_eq!(
+            part2(&input),
+            Err(OverflowError {
+                instr: Instr::Mul(i32::MIN, i32::MIN),
+                span: Span { start: 36, end: 64 },
+            })
+        );
+        assert_eq!(part2(&input), part2_many_till(&input));
+        assert_eq!(
+            part2(&input).unwrap_err().to_string(),
+            "total overflowed at mul(-2147483648,-2147483648) (bytes 36..64)"
+        );
+
+        // spans cover the source spelling, signs and leading zeros included
+        let spelled =
+            "mul(1,1)mul(-02147483648,-2147483648)mul(+0,1)mul(-2147483648,-0002147483648)";
+        assert_eq!(
+            part2_many_till(spelled),
+            Err(OverflowError {
+                instr: Instr::Mul(i32::MIN, i32::MIN),
+                span: Span { start: 46, end: 77 },
+            })
+        );
+        assert_eq!(part2(spelled), part2_many_till(spelled));
+
+        let mut machine = Machine::new(Arithmetic::Wide);
+        InstructionSet::part1().run(&mut machine, &input).unwrap();
+        assert_eq!(machine.accumulator, 2 + 2 * (1 << 62));
+
+        let mut machine = Machine::new(Arithmetic::Saturating);
+        InstructionSet::part1().run(&mut machine, &input).unwrap();
+        assert_eq!(machine.accumulator, i64::MAX.into());
+        assert_eq!(machine.executed, 4);
+
+        let mut machine = Machine::new(Arithmetic::Checked);
+        let error = InstructionSet::part1()
+            .run_reader(&mut machine, input.as_bytes())
+            .unwrap_err();
+        assert_eq!(
+            error.into_inner().unwrap().downcast_ref::<OverflowError>(),
+            part2(&input).err().as_ref()
+        );
+    }
+
+    #[test]
+    pub fn dialect_test() {
+        use Instr::*;
+        #[rustfmt::skip]
+        const CASES: [(&str, Option<Instr>, Option<Instr>); 22] = [
+            // input, strict, lenient
+            ("mul(2,4)", Some(Mul(2, 4)), Some(Mul(2, 4))),
+            ("mul(123,4)", Some(Mul(123, 4)), Some(Mul(123, 4))),
+            ("mul(0,999)", Some(Mul(0, 999)), Some(Mul(0, 999))),
+            ("mul(007,1)", Some(Mul(7, 1)), Some(Mul(7, 1))),
+            ("mul(1234,5)", None, Some(Mul(1234, 5))),
+            ("mul(5,0001)", None, Some(Mul(5, 1))),
+            ("mul(2147483647,1)", None, Some(Mul(i32::MAX, 1))),
+            ("mul(2147483648,1)", None, None),
+            ("mul(-2,4)", None, Some(Mul(-2, 4))),
+            ("mul(+2,4)", None, Some(Mul(2, 4))),
+            ("mul(2,-2147483648)", None, Some(Mul(2, i32::MIN))),
+            ("mul(--2,4)", None, None),
+            ("mul(-,4)", None, None),
+            ("mul( 2,4)", None, None),
+            ("mul(2 ,4)", None, None),
+            ("mul(2,4 )", None, None),
+            ("mul (2,4)", None, None),
+            ("mul(2,4]", None, None),
+            ("mul[3,7]", None, None),
+            ("mul(,4)", None, None),
+            ("mul(2,)", None, None),
+            ("mul(4*", None, None),
+        ];
+        for (input, strict, lenient) in CASES {
+            let parse = |dialect| parse_mul(&dialect, input).ok().map(|(_, instr)| instr);
+            assert_eq!(parse(Dialect::STRICT), strict, "strict {input:?}");
+            assert_eq!(parse(Dialect::LENIENT), lenient, "lenient {input:?}");
+        }
+
+        #[rustfmt::skip]
+        const SPACED: [(&str, Option<Instr>); 6] = [
+            ("mul( 2 , 4 )", Some(Mul(2, 4))),
+            ("mul(\t2,\n4)", Some(Mul(2, 4))),
+            ("mul(2,4)", Some(Mul(2, 4))),
+            ("mul(- 2,4)", None),
+            ("mul (2,4)", None),
+            ("mul( 1234 ,4)", None),
+        ];
+        let spaced = Dialect {
+            whitespace: true,
+            ..Dialect::STRICT
+        };
+        for (input, expected) in SPACED {
+            let found = parse_mul(&spaced, input).ok().map(|(_, instr)| instr);
+            assert_eq!(found, expected, "spaced {input:?}");
+        }
+
+        // the lenient dialect is what the free parser functions use
+        assert_eq!(Dialect::default(), Dialect::LENIENT);
+        for (input, _, lenient) in CASES {
+            let found = parse_instr(input).ok().map(|(_, instr)| instr);
+            assert_eq!(found, lenient, "parse_instr {input:?}");
+        }
+
+        const INPUT: &str = "mul(2,4)mul(1000,1)mul(-1,5)neg(12)neg(1234)mul(999,999)";
+        let set = InstructionSet::part1().register(Opcode::NEG);
+        let mut machine = Machine::default();
+        set.run(&mut machine, INPUT).unwrap();
+        assert_eq!(machine.accumulator, 8 + 1000 - 5 - 12 - 1234 + 998001);
+        let mut machine = Machine::default();
+        let strict = set.with_dialect(Dialect::STRICT);
+        strict.run(&mut machine, INPUT).unwrap();
+        assert_eq!(machine.accumulator, 8 - 12 + 998001);
+    }
+
+    #[test]
+    pub fn generate_test() {
+        for seed in 0..20 {
+            let generated = generate(seed, 10_000);
+            let memory = generated.memory.as_str();
+            assert!(memory.len() >= 10_000);
+            assert_eq!(part1(memory), Ok(generated.part1), "seed {seed}");
+            assert_eq!(part2(memory), Ok(generated.part2), "seed {seed}");
+            assert_eq!(part2_many_till(memory), Ok(generated.part2));
+
+            let strict = InstructionSet::part2().with_dialect(Dialect::STRICT);
+            let mut machine = Machine::default();
+            strict.run(&mut machine, memory).unwrap();
+            assert_eq!(machine.accumulator, generated.part2.into());
+
+            let mut machine = Machine::default();
+            InstructionSet::part2()
+                .run_reader(&mut machine, memory.as_bytes())
+                .unwrap();
+            assert_eq!(machine.accumulator, generated.part2.into());
+
+            let mut total = 0;
+            let mut rest = memory;
+            while let Ok((after, instr)) = parse_until_next_instr(rest) {
+                total += instr.product();
+                rest = after;
+            }
+            assert_eq!(total, generated.part1);
+        }
+
+        assert_eq!(generate(7, 1000), generate(7, 1000));
+        assert_ne!(generate(7, 1000), generate(8, 1000));
+        assert!(generate(0, 1000).memory.contains("don't()"));
+        assert_eq!(generate(3, 0).memory, "");
     }
 }
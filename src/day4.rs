@@ -1,22 +1,18 @@
-use std::{
-    collections::HashSet,
-    fmt::{Display, Write},
-};
+use std::fmt::{Display, Write};
 
 use aoc_runner_derive::{aoc, aoc_generator};
 
-#[repr(u8)]
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
-enum Letter {
-    Null = 0xA,
-    X = 0x58,
-    M = 0x4D,
-    A = 0x41,
-    S = 0x53,
+pub enum Letter {
+    Null,
+    X,
+    M,
+    A,
+    S,
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
-enum Direction {
+pub enum Direction {
     North,
     South,
     East,
@@ -28,8 +24,34 @@ enum Direction {
 }
 
 impl Direction {
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::South,
+        Direction::East,
+        Direction::West,
+        Direction::NorthEast,
+        Direction::NorthWest,
+        Direction::SouthEast,
+        Direction::SouthWest,
+    ];
+
+    /// The `(row, column)` step taken by `transform`.
+    pub fn delta(self) -> (isize, isize) {
+        match self {
+            Direction::North => (-1, 0),
+            Direction::South => (1, 0),
+            Direction::East => (0, 1),
+            Direction::West => (0, -1),
+            Direction::NorthEast => (-1, -1),
+            Direction::NorthWest => (-1, 1),
+            Direction::SouthEast => (1, -1),
+            Direction::SouthWest => (1, 1),
+        }
+    }
+
+    /// Moves one step from `coords`, staying within `bounds` given as `(height, width)`.
     #[inline(always)]
-    fn transform(self, coords: (usize, usize), bounds: usize) -> Option<(usize, usize)> {
+    fn transform(self, coords: (usize, usize), bounds: (usize, usize)) -> Option<(usize, usize)> {
         let (row, col) = coords;
         let transformed = match self {
             Direction::North => row.checked_sub(1).map(|row| (row, col)),
@@ -42,7 +64,8 @@ impl Direction {
             Direction::SouthWest => row.checked_add(1).zip(col.checked_add(1)),
         };
 
-        transformed.filter(|&(row, col)| row < bounds && col < bounds)
+        let (height, width) = bounds;
+        transformed.filter(|&(row, col)| row < height && col < width)
     }
 }
 
@@ -72,15 +95,13 @@ impl Display for Letter {
 
 impl Display for Grid {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for line in self.letters.chunks(self.size) {
-            for letter in line {
-                match letter.letter {
-                    Letter::Null => f.write_char('.')?,
-                    Letter::X => f.write_char('X')?,
-                    Letter::M => f.write_char('M')?,
-                    Letter::A => f.write_char('A')?,
-                    Letter::S => f.write_char('S')?,
-                }
+        if self.width == 0 {
+            return Ok(());
+        }
+
+        for line in self.letters.chunks(self.width) {
+            for cell in line {
+                f.write_char(char::from(cell.byte))?;
             }
             writeln!(f)?;
         }
@@ -88,46 +109,346 @@ impl Display for Grid {
     }
 }
 
-struct Grid {
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Grid {
     letters: Vec<Cell>,
-    // grid is square
-    size: usize,
+    width: usize,
+    height: usize,
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 #[repr(transparent)]
-struct Cell {
-    pub letter: Letter,
+pub struct Cell {
+    pub byte: u8,
+}
+
+impl Cell {
+    #[inline(always)]
+    pub fn letter(&self) -> Letter {
+        Letter::from(self.byte)
+    }
+}
+
+/// A word found in the grid, spelled from `start` one step at a time in `direction`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct WordMatch {
+    /// Index of the word in the list searched for.
+    pub word: usize,
+    pub start: (usize, usize),
+    pub direction: Direction,
+    pub len: usize,
+}
+
+/// A small 2D pattern of letters, where `None` matches any letter.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Template {
+    cells: Vec<Option<u8>>,
+    width: usize,
+    height: usize,
+}
+
+/// Why a pattern isn't a template.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TemplateError {
+    /// The pattern has no rows, which would match everywhere.
+    Empty,
+    /// A `\` at the end of a row (1-based) with nothing to escape.
+    DanglingEscape { row: usize },
+}
+
+impl Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TemplateError::Empty => f.write_str("empty template"),
+            TemplateError::DanglingEscape { row } => {
+                write!(f, "nothing to escape at the end of row {row}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// Which transformations of a template are also looked for.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Symmetry {
+    /// Only the template as written.
+    None,
+    /// The four quarter turns.
+    Rotations,
+    /// The four quarter turns, and those of the mirror image.
+    All,
+}
+
+/// Where a template matched, by the top left corner of its bounding box.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct TemplateMatch {
+    pub top_left: (usize, usize),
+    /// Index into the orientations of the template that matched.
+    pub orientation: usize,
+}
+
+impl WordMatch {
+    /// The coordinates of each letter of the match, in order.
+    pub fn cells(&self) -> impl Iterator<Item = (usize, usize)> {
+        let (row, col) = self.start;
+        let (row_step, col_step) = self.direction.delta();
+        (0..self.len as isize).map(move |i| {
+            (
+                (row as isize + row_step * i) as usize,
+                (col as isize + col_step * i) as usize,
+            )
+        })
+    }
+}
+
+impl Template {
+    /// Parses rows separated by newlines or `/`, ignoring surrounding whitespace, where `.` is a
+    /// wildcard and `\` makes the next letter literal, so `\.`, `\/` and `\\` match a dot, a
+    /// slash and a backslash. Short rows are padded with wildcards, blank rows between others
+    /// included, and blank rows at the start and end are dropped.
+    pub fn parse(pattern: &str) -> Result<Template, TemplateError> {
+        let bytes = pattern.as_bytes();
+        let mut raw_rows = Vec::new();
+        let (mut start, mut index) = (0, 0);
+        while index < bytes.len() {
+            match bytes[index] {
+                b'\\' => index += 1,
+                b'\n' | b'/' => {
+                    raw_rows.push(&bytes[start..index]);
+                    start = index + 1;
+                }
+                _ => {}
+            }
+            index += 1;
+        }
+        raw_rows.push(&bytes[start..]);
+
+        let mut rows: Vec<Vec<Option<u8>>> = Vec::new();
+        for (index, row) in raw_rows.into_iter().enumerate() {
+            let mut letters = row.trim_ascii().iter();
+            let mut cells = Vec::new();
+            while let Some(&byte) = letters.next() {
+                cells.push(match byte {
+                    b'.' => None,
+                    b'\\' => match letters.next() {
+                        Some(&escaped) => Some(escaped),
+                        None => return Err(TemplateError::DanglingEscape { row: index + 1 }),
+                    },
+                    _ => Some(byte),
+                });
+            }
+            rows.push(cells);
+        }
+
+        let (Some(first), Some(last)) = (
+            rows.iter().position(|row| !row.is_empty()),
+            rows.iter().rposition(|row| !row.is_empty()),
+        ) else {
+            return Err(TemplateError::Empty);
+        };
+        let rows = &rows[first..=last];
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        let cells = rows
+            .iter()
+            .flat_map(|row| (0..width).map(|col| row.get(col).copied().flatten()))
+            .collect();
+
+        Ok(Template {
+            cells,
+            width,
+            height: rows.len(),
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    #[inline(always)]
+    pub fn at(&self, coords: (usize, usize)) -> Option<u8> {
+        let (row, col) = coords;
+        self.cells[row * self.width + col]
+    }
+
+    /// The coordinates the letters of the template cover with its top left corner at `top_left`.
+    pub fn cells(&self, top_left: (usize, usize)) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let (top, left) = top_left;
+        (0..self.cells.len())
+            .filter(|&index| self.cells[index].is_some())
+            .map(move |index| (top + index / self.width, left + index % self.width))
+    }
+
+    /// The template turned a quarter clockwise.
+    pub fn rotated(&self) -> Template {
+        let cells = (0..self.width)
+            .flat_map(|row| (0..self.height).map(move |col| (self.height - 1 - col, row)))
+            .map(|coords| self.at(coords))
+            .collect();
+
+        Template {
+            cells,
+            width: self.height,
+            height: self.width,
+        }
+    }
+
+    /// The template mirrored left to right.
+    pub fn reflected(&self) -> Template {
+        let cells = (0..self.height)
+            .flat_map(|row| (0..self.width).map(move |col| (row, self.width - 1 - col)))
+            .map(|coords| self.at(coords))
+            .collect();
+
+        Template { cells, ..*self }
+    }
+
+    /// The distinct transformations of the template allowed by `symmetry`, starting with the
+    /// template itself.
+    pub fn orientations(&self, symmetry: Symmetry) -> Vec<Template> {
+        let mut orientations = vec![self.clone()];
+        let mut add_rotations = |start: Template| {
+            let mut current = start;
+            for _ in 0..4 {
+                if !orientations.contains(&current) {
+                    orientations.push(current.clone());
+                }
+                current = current.rotated();
+            }
+        };
+
+        match symmetry {
+            Symmetry::None => {}
+            Symmetry::Rotations => add_rotations(self.clone()),
+            Symmetry::All => {
+                add_rotations(self.clone());
+                add_rotations(self.reflected());
+            }
+        }
+
+        orientations
+    }
+}
+
+/// Whether `byte` can be a letter in a grid, being printable ASCII.
+#[inline(always)]
+fn is_letter(byte: u8) -> bool {
+    byte.wrapping_sub(b'!') <= b'~' - b'!'
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ParseErrorKind {
+    /// A byte that isn't printable ASCII.
+    InvalidByte(u8),
+    /// A row with a different number of letters than the first.
+    RaggedRow { expected: usize },
+}
+
+/// An error in a grid, pointing at the offending line (1-based) and byte column (1-based).
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub line: usize,
+    pub column: usize,
 }
 
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            ParseErrorKind::InvalidByte(byte) => write!(f, "invalid byte {byte:#04x}")?,
+            ParseErrorKind::RaggedRow { expected } => {
+                write!(f, "row is not {expected} letters long")?
+            }
+        }
+
+        write!(f, " at line {}, column {}", self.line, self.column)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
 #[aoc_generator(day4)]
-pub fn input_generator(input: &str) -> Grid {
+pub fn input_generator(input: &str) -> Result<Grid, ParseError> {
     Grid::parse(input)
 }
 
+/// Colours for `Grid::render_ansi`, one per direction.
+const PALETTE: [u8; 8] = [31, 32, 33, 34, 35, 36, 91, 94];
+
 impl Grid {
     pub fn len(&self) -> usize {
         self.letters.len()
     }
 
-    pub fn parse(input: &str) -> Grid {
-        // SAFETY: `Letter` is constructed such that it is transmutable from bytes, where newlines are the 'Null' character.
-        // This makes parsing extremely fast.
-        let mut letters = Vec::from(unsafe { std::mem::transmute::<_, &[Cell]>(input.as_bytes()) });
-        let size = letters
-            .iter()
-            .enumerate()
-            .find(|(_, s)| s.letter == Letter::Null)
-            .unwrap()
-            .0;
+    pub fn is_empty(&self) -> bool {
+        self.letters.is_empty()
+    }
 
-        letters.retain(|s| s.letter != Letter::Null);
-        Grid { letters, size }
+    pub fn width(&self) -> usize {
+        self.width
     }
 
-    #[inline(always)]
-    pub fn at_index_mut(&mut self, index: usize) -> Option<&mut Cell> {
-        self.letters.get_mut(index)
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Parses rows of printable ASCII letters, tolerating CRLF line endings, trailing whitespace
+    /// on each row and blank lines at the end.
+    ///
+    /// Only bytes from `!` to `~` are letters, so a grid can't hold spaces, which would be lost to
+    /// the trimming, or non-ASCII text, whose characters span several bytes and so several cells.
+    /// Anything else is an `InvalidByte`.
+    pub fn parse(input: &str) -> Result<Grid, ParseError> {
+        let mut letters = Vec::with_capacity(input.len());
+        let mut width = None;
+        let mut height = 0;
+        let mut blank = 0;
+        for (index, line) in input.as_bytes().split(|&b| b == b'\n').enumerate() {
+            let line = line.trim_ascii_end();
+            if line.is_empty() {
+                blank += 1;
+                continue;
+            }
+
+            if let (Some(expected), 1..) = (width, blank) {
+                // A blank line between rows is a row with no letters.
+                return Err(ParseError {
+                    kind: ParseErrorKind::RaggedRow { expected },
+                    line: index + 1 - blank,
+                    column: 1,
+                });
+            }
+            blank = 0;
+
+            let error = |kind, column| ParseError {
+                kind,
+                line: index + 1,
+                column,
+            };
+            // Checking the whole row without branching first lets this vectorise.
+            if !line.iter().fold(true, |valid, &b| valid & is_letter(b)) {
+                let column = line.iter().position(|&b| !is_letter(b)).unwrap();
+                return Err(error(ParseErrorKind::InvalidByte(line[column]), column + 1));
+            }
+            let expected = *width.get_or_insert(line.len());
+            if line.len() != expected {
+                let column = line.len().min(expected) + 1;
+                return Err(error(ParseErrorKind::RaggedRow { expected }, column));
+            }
+
+            letters.extend(line.iter().map(|&byte| Cell { byte }));
+            height += 1;
+        }
+
+        Ok(Grid {
+            letters,
+            width: width.unwrap_or(0),
+            height,
+        })
     }
 
     #[inline(always)]
@@ -137,16 +458,15 @@ impl Grid {
 
     #[inline(always)]
     pub fn index_to_coordinate(&self, index: usize) -> (usize, usize) {
-        let column = index % self.size;
-        let row = index / self.size;
+        let column = index % self.width;
+        let row = index / self.width;
         (row, column)
     }
 
     #[inline(always)]
     pub fn coordinate_to_index(&self, coords: (usize, usize)) -> usize {
         let (row, column) = coords;
-        let index = row * self.size + column;
-        index
+        row * self.width + column
     }
 
     #[inline(always)]
@@ -156,150 +476,349 @@ impl Grid {
         direction: Direction,
     ) -> Option<(&Cell, usize, Direction)> {
         direction
-            .transform(self.index_to_coordinate(index), self.size)
+            .transform(self.index_to_coordinate(index), (self.height, self.width))
             .map(|c| self.coordinate_to_index(c))
             .and_then(|i| self.at_index(i).map(|s| (s, i, direction)))
     }
-}
-
-#[aoc(day4, part1)]
-pub fn part1(grid: &Grid) -> u32 {
-    const ALL_DIRECTIONS: &[Direction] = &[
-        Direction::North,
-        Direction::South,
-        Direction::East,
-        Direction::West,
-        Direction::NorthEast,
-        Direction::NorthWest,
-        Direction::SouthEast,
-        Direction::SouthWest,
-    ];
-
-    let mut valid_xs = 0;
 
-    for x_index in 0..grid.len() {
-        let Some(cell) = grid.at_index(x_index) else {
-            continue;
+    /// Whether `word` is spelled from `start` in `direction`.
+    pub fn spells(&self, word: &[u8], start: (usize, usize), direction: Direction) -> bool {
+        let Some(last) = word.len().checked_sub(1) else {
+            return true;
         };
+        let (row, column) = start;
+        let (row_step, column_step) = direction.delta();
+        let end_row = row as isize + row_step * last as isize;
+        let end_column = column as isize + column_step * last as isize;
+        if row >= self.height
+            || column >= self.width
+            || !(0..self.height as isize).contains(&end_row)
+            || !(0..self.width as isize).contains(&end_column)
+        {
+            return false;
+        }
 
-        if cell.letter != Letter::X {
-            continue;
+        let stride = row_step * self.width as isize + column_step;
+        let start = self.coordinate_to_index(start) as isize;
+        word.iter()
+            .enumerate()
+            .all(|(i, &byte)| self.letters[(start + stride * i as isize) as usize].byte == byte)
+    }
+
+    /// The grid with every letter outside of `marked` replaced by a dot.
+    pub fn render(&self, marked: impl IntoIterator<Item = (usize, usize)>) -> String {
+        let mut shown = vec![false; self.len()];
+        for coords in marked {
+            shown[self.coordinate_to_index(coords)] = true;
         }
 
-        let ms = ALL_DIRECTIONS
-            .iter()
-            .map(|d| grid.at_direction(x_index, *d))
-            .filter_map(|s| s)
-            .filter_map(|(s, m_index, direction)| {
-                if s.letter == Letter::M {
-                    Some((m_index, direction))
+        let width = self.width.max(1);
+        let mut rendered = String::with_capacity(self.len() + self.height);
+        for (line, shown) in self.letters.chunks(width).zip(shown.chunks(width)) {
+            rendered.extend(line.iter().zip(shown).map(|(cell, &shown)| {
+                if shown {
+                    char::from(cell.byte)
                 } else {
-                    None
+                    '.'
                 }
-            });
+            }));
+            rendered.push('\n');
+        }
+        rendered
+    }
 
-        for (m_index, direction) in ms {
-            let Some((cell, a_index, _)) = grid.at_direction(m_index, direction) else {
-                continue;
-            };
+    /// The grid with each letter in `marked` coloured by its group, such as the index of its
+    /// direction, and the rest dimmed. A letter marked more than once takes the last colour.
+    pub fn render_ansi(&self, marked: impl IntoIterator<Item = ((usize, usize), usize)>) -> String {
+        let mut groups = vec![None; self.len()];
+        for (coords, group) in marked {
+            groups[self.coordinate_to_index(coords)] = Some(group);
+        }
 
-            if cell.letter != Letter::A {
-                continue;
+        let width = self.width.max(1);
+        let mut rendered = String::new();
+        for (line, groups) in self.letters.chunks(width).zip(groups.chunks(width)) {
+            for (cell, group) in line.iter().zip(groups) {
+                let letter = char::from(cell.byte);
+                // Writing to a String can't fail.
+                let _ = match group {
+                    Some(group) => write!(
+                        rendered,
+                        "\x1b[1;{}m{letter}\x1b[0m",
+                        PALETTE[group % PALETTE.len()]
+                    ),
+                    None => write!(rendered, "\x1b[2m{letter}\x1b[0m"),
+                };
             }
+            rendered.push('\n');
+        }
+        rendered
+    }
 
-            let Some((cell, _s_index, _)) = grid.at_direction(a_index, direction) else {
-                continue;
-            };
+    /// `render_ansi` of word matches, each letter coloured by the direction of its word.
+    pub fn render_words_ansi(&self, matches: &[WordMatch]) -> String {
+        self.render_ansi(matches.iter().flat_map(|found| {
+            found
+                .cells()
+                .map(move |cell| (cell, found.direction as usize))
+        }))
+    }
 
-            if cell.letter != Letter::S {
-                continue;
+    /// `render_ansi` of the matches `find_template` returned for `template` and `symmetry`, each
+    /// letter coloured by the orientation that matched.
+    pub fn render_template_ansi(
+        &self,
+        template: &Template,
+        symmetry: Symmetry,
+        matches: &[TemplateMatch],
+    ) -> String {
+        let orientations = template.orientations(symmetry);
+        self.render_ansi(matches.iter().flat_map(|found| {
+            orientations[found.orientation]
+                .cells(found.top_left)
+                .map(move |cell| (cell, found.orientation))
+        }))
+    }
+
+    /// Whether `template` matches with its top left corner at `top_left`.
+    pub fn fits(&self, template: &Template, top_left: (usize, usize)) -> bool {
+        let (top, left) = top_left;
+        if top + template.height > self.height || left + template.width > self.width {
+            return false;
+        }
+
+        (0..template.height).all(|row| {
+            let start = self.coordinate_to_index((top + row, left));
+            let line = &self.letters[start..start + template.width];
+            line.iter()
+                .enumerate()
+                .all(|(col, cell)| template.at((row, col)).is_none_or(|byte| byte == cell.byte))
+        })
+    }
+
+    /// Every place `template`, or one of its orientations under `symmetry`, matches, in reading
+    /// order. Orientations that look the same are only tried once.
+    pub fn find_template(&self, template: &Template, symmetry: Symmetry) -> Vec<TemplateMatch> {
+        let orientations = template.orientations(symmetry);
+        let mut matches = Vec::new();
+        for top in 0..self.height {
+            for left in 0..self.width {
+                for (orientation, template) in orientations.iter().enumerate() {
+                    if self.fits(template, (top, left)) {
+                        matches.push(TemplateMatch {
+                            top_left: (top, left),
+                            orientation,
+                        });
+                    }
+                }
             }
+        }
 
-            valid_xs += 1;
+        matches
+    }
+
+    /// Every occurrence of each of `words` in any of the eight directions, in reading order of
+    /// their first letter. Single letters are only reported once, going `East`, and empty words
+    /// never match.
+    ///
+    /// Words can be any bytes, but only those `Grid::parse` accepts as letters can match.
+    pub fn find_words(&self, words: &[&[u8]]) -> Vec<WordMatch> {
+        let mut matches = Vec::new();
+        for (index, cell) in self.letters.iter().enumerate() {
+            for (word, letters) in words.iter().enumerate() {
+                if letters.first() != Some(&cell.byte) {
+                    continue;
+                }
+
+                let directions: &[Direction] = if letters.len() == 1 {
+                    &[Direction::East]
+                } else {
+                    &Direction::ALL
+                };
+                let start = self.index_to_coordinate(index);
+                for &direction in directions {
+                    if self.spells(letters, start, direction) {
+                        matches.push(WordMatch {
+                            word,
+                            start,
+                            direction,
+                            len: letters.len(),
+                        });
+                    }
+                }
+            }
         }
+
+        matches
     }
+}
+
+pub const XMAS: &[u8] = b"XMAS";
 
-    valid_xs
+/// Two MAS crossing, each of which may be written backwards.
+pub const X_MAS: &str = "M.S / .A. / M.S";
+
+/// Every XMAS in the grid.
+pub fn part1_matches(grid: &Grid) -> Vec<WordMatch> {
+    grid.find_words(&[XMAS])
+}
+
+/// Every X-MAS in the grid, as orientations of `X_MAS` under `Symmetry::Rotations`.
+pub fn part2_matches(grid: &Grid) -> Vec<TemplateMatch> {
+    let x_mas = Template::parse(X_MAS).expect("X_MAS is a valid template");
+    grid.find_template(&x_mas, Symmetry::Rotations)
+}
+
+#[aoc(day4, part1)]
+pub fn part1(grid: &Grid) -> u32 {
+    part1_matches(grid).len() as u32
 }
 
 #[aoc(day4, part2)]
 pub fn part2(grid: &Grid) -> u32 {
-    let mut valid_as = 0;
+    part2_matches(grid).len() as u32
+}
 
-    for a_index in 0..grid.len() {
-        let Some(cell) = grid.at_index(a_index) else {
-            continue;
-        };
+#[aoc(day4, part1, Bitboard)]
+pub fn part1_bitboard(grid: &Grid) -> u32 {
+    Bitboards::new(grid).count_xmas()
+}
 
-        if cell.letter != Letter::A {
-            continue;
-        }
+#[aoc(day4, part2, Bitboard)]
+pub fn part2_bitboard(grid: &Grid) -> u32 {
+    Bitboards::new(grid).count_x_mas()
+}
 
-        // Check NorthEast corner
-        let Some((cell, _index, _)) = grid.at_direction(a_index, Direction::NorthEast) else {
-            continue;
-        };
+/// The grid as one bitboard per letter of XMAS, each row a run of words where bit `c % 64` of
+/// word `c / 64` is set when the letter is in column `c`. Occurrences are counted for whole
+/// rows at a time by shifting and ANDing neighbouring rows.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Bitboards {
+    height: usize,
+    /// Words per row.
+    stride: usize,
+    /// Rows of X, M, A and S.
+    planes: [Vec<u64>; 4],
+}
 
-        if cell.letter != Letter::M && cell.letter != Letter::S {
-            continue;
-        }
+impl Bitboards {
+    // Indices of the planes, in the order of XMAS.
+    const X: usize = 0;
+    const M: usize = 1;
+    const A: usize = 2;
+    const S: usize = 3;
 
-        // if NE is M, then SW must be S
-        if cell.letter == Letter::M
-            && !grid
-                .at_direction(a_index, Direction::SouthWest)
-                .is_some_and(|(cell, _, _)| cell.letter == Letter::S)
-        {
-            continue;
-        } else if cell.letter == Letter::S
-            && !grid
-                .at_direction(a_index, Direction::SouthWest)
-                .is_some_and(|(cell, _, _)| cell.letter == Letter::M)
-        {
-            continue;
+    pub fn new(grid: &Grid) -> Bitboards {
+        let stride = grid.width.div_ceil(64);
+        let mut planes: [Vec<u64>; 4] = std::array::from_fn(|_| vec![0; stride * grid.height]);
+        for (row, line) in grid.letters.chunks(grid.width.max(1)).enumerate() {
+            for (word, cells) in line.chunks(64).enumerate() {
+                // Comparing against every letter avoids a branch per cell.
+                let mut bits = [0u64; 4];
+                for (col, cell) in cells.iter().enumerate() {
+                    for (bits, letter) in bits.iter_mut().zip(*b"XMAS") {
+                        *bits |= u64::from(cell.byte == letter) << col;
+                    }
+                }
+                for (plane, bits) in planes.iter_mut().zip(bits) {
+                    plane[row * stride + word] = bits;
+                }
+            }
         }
 
-        // KNOWN
-        // M  ?       S  ?
-        //   A    OR   A
-        // ?  S      ?  M
+        Bitboards {
+            height: grid.height,
+            stride,
+            planes,
+        }
+    }
 
-        // Check NorthWest corner
-        let Some((cell, _index, _)) = grid.at_direction(a_index, Direction::NorthWest) else {
-            continue;
-        };
+    #[inline(always)]
+    fn row(&self, plane: usize, row: usize) -> &[u64] {
+        &self.planes[plane][row * self.stride..(row + 1) * self.stride]
+    }
 
-        if cell.letter != Letter::M && cell.letter != Letter::S {
-            continue;
-        } else if cell.letter == Letter::M
-            && !grid
-                .at_direction(a_index, Direction::SouthEast)
-                .is_some_and(|(cell, _, _)| cell.letter == Letter::S)
-        {
-            continue;
+    /// Word `word` of `row` moved so that each column holds what was `shift` columns east of it.
+    #[inline(always)]
+    fn shifted(row: &[u64], word: usize, shift: isize) -> u64 {
+        let amount = shift.unsigned_abs() as u32;
+        match shift {
+            0 => row[word],
+            1.. => {
+                let carry = row.get(word + 1).map_or(0, |next| next << (64 - amount));
+                row[word] >> amount | carry
+            }
+            _ => {
+                let carry = word
+                    .checked_sub(1)
+                    .map_or(0, |previous| row[previous] >> (64 - amount));
+                row[word] << amount | carry
+            }
         }
+    }
 
-        // if NW is S, then SE must be M
-        if cell.letter == Letter::S
-            && !grid
-                .at_direction(a_index, Direction::SouthEast)
-                .is_some_and(|(cell, _, _)| cell.letter == Letter::M)
-        {
-            continue;
+    /// Occurrences of XMAS in all eight directions.
+    pub fn count_xmas(&self) -> u32 {
+        let mut count = 0;
+        for direction in Direction::ALL {
+            let (row_step, col_step) = direction.delta();
+            for row in 0..self.height as isize {
+                let last = row + 3 * row_step;
+                if !(0..self.height as isize).contains(&last) {
+                    continue;
+                }
+
+                let letters: [&[u64]; 4] =
+                    std::array::from_fn(|i| self.row(i, (row + i as isize * row_step) as usize));
+                for word in 0..self.stride {
+                    let found = letters[Self::X][word]
+                        & Self::shifted(letters[Self::M], word, col_step)
+                        & Self::shifted(letters[Self::A], word, 2 * col_step)
+                        & Self::shifted(letters[Self::S], word, 3 * col_step);
+                    count += found.count_ones();
+                }
+            }
         }
 
-        valid_as += 1;
+        count
     }
 
-    valid_as
+    /// Occurrences of two crossing MAS diagonals.
+    pub fn count_x_mas(&self) -> u32 {
+        let mut count = 0;
+        for row in 1..self.height.saturating_sub(1) {
+            let a = self.row(Self::A, row);
+            let (m_above, s_above) = (self.row(Self::M, row - 1), self.row(Self::S, row - 1));
+            let (m_below, s_below) = (self.row(Self::M, row + 1), self.row(Self::S, row + 1));
+            for (word, centre) in a.iter().enumerate() {
+                let corner = |row: &[u64], shift| Self::shifted(row, word, shift);
+                let falling = corner(m_above, -1) & corner(s_below, 1)
+                    | corner(s_above, -1) & corner(m_below, 1);
+                let rising = corner(m_above, 1) & corner(s_below, -1)
+                    | corner(s_above, 1) & corner(m_below, -1);
+                count += (centre & falling & rising).count_ones();
+            }
+        }
+
+        count
+    }
 }
+
 #[cfg(test)]
 mod test {
-    use crate::day4::{part1, part2, Direction, Grid, Letter};
+    use crate::day4::{
+        part1, part1_bitboard, part1_matches, part2, part2_bitboard, part2_matches, Direction,
+        Grid, Letter, ParseError, ParseErrorKind, Symmetry, Template, TemplateError, TemplateMatch,
+        WordMatch, PALETTE, X_MAS,
+    };
+    use proptest::{
+        prelude::RngExt,
+        test_runner::{RngAlgorithm, TestRng},
+    };
 
     #[test]
     pub fn part1_lib_test() {
-        assert_eq!(Direction::South.transform((3, 9), 10), Some((4, 9)));
+        assert_eq!(Direction::South.transform((3, 9), (10, 10)), Some((4, 9)));
 
         const INPUT: &str = r#"MMMSXXMASM
 MSAMXMSMSA
@@ -313,7 +832,7 @@ MAMMMXMMMM
 MXMXAXMASX
 "#;
 
-        let grid = Grid::parse(INPUT);
+        let grid = Grid::parse(INPUT).unwrap();
         assert_eq!(grid.to_string(), INPUT);
         assert_eq!(grid.index_to_coordinate(0), (0, 0));
         assert_eq!(grid.index_to_coordinate(1), (0, 1));
@@ -327,13 +846,13 @@ MXMXAXMASX
         assert_eq!(grid.coordinate_to_index((0, 1)), 1);
         assert_eq!(grid.coordinate_to_index((0, 5)), 5);
 
-        assert_eq!(Direction::East.transform((0, 5), 10), Some((0, 6)));
+        assert_eq!(Direction::East.transform((0, 5), (10, 10)), Some((0, 6)));
 
         assert_eq!(
             grid.at_direction(grid.coordinate_to_index((3, 9)), Direction::South)
                 .unwrap()
                 .0
-                .letter,
+                .letter(),
             Letter::M
         );
 
@@ -341,7 +860,7 @@ MXMXAXMASX
             grid.at_direction(grid.coordinate_to_index((0, 4)), Direction::East)
                 .unwrap()
                 .0
-                .letter,
+                .letter(),
             Letter::X
         );
     }
@@ -360,7 +879,7 @@ MAMMMXMMMM
 MXMXAXMASX
 "#;
 
-        assert_eq!(part1(&Grid::parse(INPUT)), 18);
+        assert_eq!(part1(&Grid::parse(INPUT).unwrap()), 18);
     }
 
     #[test]
@@ -377,7 +896,7 @@ MAMMMXMMMM
 MXMXAXMASX
 "#;
 
-        assert_eq!(part2(&Grid::parse(INPUT)), 9);
+        assert_eq!(part2(&Grid::parse(INPUT).unwrap()), 9);
     }
 
     #[test]
@@ -396,7 +915,520 @@ AAAMAASXMMMS
 MMMMMXMAAXAS
 "#;
 
-        assert_eq!(part1(&Grid::parse(INPUT)), 8);
+        assert_eq!(part1(&Grid::parse(INPUT).unwrap()), 8);
+    }
+
+    /// Counts XMAS and X-MAS by brute force over rows of bytes.
+    fn reference(rows: &[&str]) -> (u32, u32) {
+        let at = |row: isize, col: isize| {
+            usize::try_from(row)
+                .ok()
+                .and_then(|row| rows.get(row))
+                .zip(usize::try_from(col).ok())
+                .and_then(|(line, col)| line.as_bytes().get(col).copied())
+        };
+
+        let (mut xmas, mut x_mas) = (0, 0);
+        for row in 0..rows.len() as isize {
+            for col in 0..rows[0].len() as isize {
+                for (dr, dc) in [
+                    (-1, -1),
+                    (-1, 0),
+                    (-1, 1),
+                    (0, -1),
+                    (0, 1),
+                    (1, -1),
+                    (1, 0),
+                    (1, 1),
+                ] {
+                    if (0..4).all(|i| at(row + dr * i, col + dc * i) == Some(b"XMAS"[i as usize])) {
+                        xmas += 1;
+                    }
+                }
+
+                let diagonal = |a, b| {
+                    matches!(
+                        (at(row - 1, col + a), at(row + 1, col + b)),
+                        (Some(b'M'), Some(b'S')) | (Some(b'S'), Some(b'M'))
+                    )
+                };
+                if at(row, col) == Some(b'A') && diagonal(-1, 1) && diagonal(1, -1) {
+                    x_mas += 1;
+                }
+            }
+        }
+        (xmas, x_mas)
+    }
+
+    #[test]
+    pub fn rectangular_test() {
+        const EXAMPLE: [&str; 10] = [
+            "MMMSXXMASM",
+            "MSAMXMSMSA",
+            "AMXSXMAAMM",
+            "MSAMASMSMX",
+            "XMASAMXAMM",
+            "XXAMMXXAMA",
+            "SMSMSASXSS",
+            "SAXAMASAAA",
+            "MAMMMXMMMM",
+            "MXMXAXMASX",
+        ];
+
+        let transpose = |rows: &[&str]| -> Vec<String> {
+            (0..rows[0].len())
+                .map(|col| {
+                    rows.iter()
+                        .map(|row| char::from(row.as_bytes()[col]))
+                        .collect()
+                })
+                .collect()
+        };
+        let wide: Vec<String> = EXAMPLE[..4]
+            .iter()
+            .map(|row| format!("{row}{}", row.chars().rev().collect::<String>()))
+            .collect();
+        let tall = transpose(&wide.iter().map(String::as_str).collect::<Vec<_>>());
+
+        for rows in [&wide, &tall] {
+            let rows: Vec<&str> = rows.iter().map(String::as_str).collect();
+            let input: String = rows.iter().map(|row| format!("{row}\n")).collect();
+            let grid = Grid::parse(&input).unwrap();
+            assert_eq!((grid.height(), grid.width()), (rows.len(), rows[0].len()));
+            assert_eq!(grid.to_string(), input);
+
+            let last = grid.len() - 1;
+            assert_eq!(
+                grid.index_to_coordinate(last),
+                (rows.len() - 1, rows[0].len() - 1)
+            );
+            assert_eq!(
+                grid.coordinate_to_index((rows.len() - 1, 0)),
+                last + 1 - rows[0].len()
+            );
+            assert!(grid.at_direction(last, Direction::South).is_none());
+            assert!(grid.at_direction(last, Direction::East).is_none());
+            assert!(grid
+                .at_direction(rows[0].len() - 1, Direction::East)
+                .is_none());
+
+            let (xmas, x_mas) = reference(&rows);
+            assert!(xmas > 0 && x_mas > 0);
+            assert_eq!(part1(&grid), xmas);
+            assert_eq!(part2(&grid), x_mas);
+        }
+
+        let (xmas, x_mas) = reference(&EXAMPLE);
+        assert_eq!((xmas, x_mas), (18, 9));
+
+        assert_eq!(part1(&Grid::parse("XMAS").unwrap()), 1);
+        assert_eq!(part1(&Grid::parse("X\nM\nA\nS\n").unwrap()), 1);
+        assert_eq!(part1(&Grid::parse("SAMXMAS\n").unwrap()), 2);
+        assert_eq!(Grid::parse("").unwrap().to_string(), "");
+    }
+
+    #[test]
+    pub fn find_words_test() {
+        const INPUT: &str = "cat.\ntoad\n.d0g\n";
+        let grid = Grid::parse(INPUT).unwrap();
+        assert_eq!(grid.to_string(), INPUT);
+
+        let matches = grid.find_words(&[b"cat", b"dog", b"tac", b"ad", b"0", b"", b"zebra"]);
+        assert_eq!(
+            matches,
+            [
+                WordMatch {
+                    word: 0,
+                    start: (0, 0),
+                    direction: Direction::East,
+                    len: 3
+                },
+                WordMatch {
+                    word: 2,
+                    start: (0, 2),
+                    direction: Direction::West,
+                    len: 3
+                },
+                WordMatch {
+                    word: 3,
+                    start: (1, 2),
+                    direction: Direction::East,
+                    len: 2
+                },
+                WordMatch {
+                    word: 3,
+                    start: (1, 2),
+                    direction: Direction::SouthEast,
+                    len: 2
+                },
+                WordMatch {
+                    word: 4,
+                    start: (2, 2),
+                    direction: Direction::East,
+                    len: 1
+                },
+            ]
+        );
+
+        assert!(grid.spells(b"toad", (1, 0), Direction::East));
+        assert!(grid.spells(b"dao", (1, 3), Direction::West));
+        assert!(!grid.spells(b"toad!", (1, 0), Direction::East));
+        assert!(!grid.spells(b"d", (1, 4), Direction::East));
+        assert!(!grid.spells(b"ta", (0, 0), Direction::North));
+
+        // every letter of a match is where the direction says it is
+        const EXAMPLE: &str = "MMMSXXMASM\nMSAMXMSMSA\nAMXSXMAAMM\nMSAMASMSMX\nXMASAMXAMM\n";
+        let grid = Grid::parse(EXAMPLE).unwrap();
+        let matches = grid.find_words(&[b"XMAS", b"SAM"]);
+        let rows: Vec<&str> = EXAMPLE.lines().collect();
+        assert_eq!(
+            matches.iter().filter(|m| m.word == 0).count(),
+            reference(&rows).0 as usize
+        );
+        for found in matches {
+            let word: &[u8] = [&b"XMAS"[..], b"SAM"][found.word];
+            let mut index = grid.coordinate_to_index(found.start);
+            assert_eq!(grid.at_index(index).unwrap().byte, word[0]);
+            for &letter in &word[1..] {
+                let (cell, next, _) = grid.at_direction(index, found.direction).unwrap();
+                assert_eq!(cell.byte, letter);
+                index = next;
+            }
+        }
+    }
+
+    #[test]
+    pub fn template_test() {
+        let x_mas = Template::parse("M.S / .A. / M.S").unwrap();
+        assert_eq!(x_mas, Template::parse("M.S\n.A.\nM.S\n").unwrap());
+        assert_eq!((x_mas.height(), x_mas.width()), (3, 3));
+        assert_eq!(x_mas.at((0, 0)), Some(b'M'));
+        assert_eq!(x_mas.at((0, 1)), None);
+        assert_eq!(x_mas.rotated(), Template::parse("M.M/.A./S.S").unwrap());
+        assert_eq!(x_mas.reflected(), Template::parse("S.M/.A./S.M").unwrap());
+        assert_eq!(x_mas.rotated().rotated().rotated().rotated(), x_mas);
+        assert_eq!(x_mas.orientations(Symmetry::None).len(), 1);
+        assert_eq!(x_mas.orientations(Symmetry::Rotations).len(), 4);
+        // mirror images of the cross are rotations of it
+        assert_eq!(x_mas.orientations(Symmetry::All).len(), 4);
+
+        let ell = Template::parse("A. / A. / AB").unwrap();
+        assert_eq!(ell.rotated(), Template::parse("AAA/B..").unwrap());
+        assert_eq!(ell.orientations(Symmetry::Rotations).len(), 4);
+        assert_eq!(ell.orientations(Symmetry::All).len(), 8);
+        assert_eq!(
+            Template::parse("A.A/.../A.A")
+                .unwrap()
+                .orientations(Symmetry::All)
+                .len(),
+            1
+        );
+        assert_eq!(
+            Template::parse("AB/C").unwrap(),
+            Template::parse("AB/C.").unwrap()
+        );
+
+        const INPUT: &str = "..A.\n.AAA\n..AB\n";
+        let grid = Grid::parse(INPUT).unwrap();
+        assert_eq!(
+            grid.find_template(&ell, Symmetry::None),
+            [TemplateMatch {
+                top_left: (0, 2),
+                orientation: 0
+            }]
+        );
+        let found = grid.find_template(&ell, Symmetry::All);
+        let orientations = ell.orientations(Symmetry::All);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[1].top_left, (1, 1));
+        for TemplateMatch {
+            top_left,
+            orientation,
+        } in found
+        {
+            assert!(grid.fits(&orientations[orientation], top_left));
+        }
+        assert!(grid.fits(&Template::parse("....").unwrap(), (2, 0)));
+        assert!(!grid.fits(&Template::parse(".....").unwrap(), (0, 0)));
+        assert!(!grid.fits(&ell, (1, 0)));
+        assert!(grid
+            .find_template(&Template::parse("AAAAA").unwrap(), Symmetry::All)
+            .is_empty());
+
+        // blank rows in the middle are rows of wildcards
+        let gap = Template::parse("\nA//B\n\n").unwrap();
+        assert_eq!((gap.height(), gap.width()), (3, 1));
+        assert_eq!(gap, Template::parse("A\n \nB").unwrap());
+        assert_eq!(gap, Template::parse("A/./B").unwrap());
+        assert_ne!(gap, Template::parse("A/B").unwrap());
+
+        // an empty pattern would match everywhere
+        assert_eq!(Template::parse(""), Err(TemplateError::Empty));
+        assert_eq!(Template::parse(" / \n"), Err(TemplateError::Empty));
+        assert_eq!(
+            Template::parse("A/B\\"),
+            Err(TemplateError::DanglingEscape { row: 2 })
+        );
+        assert_eq!(
+            TemplateError::DanglingEscape { row: 2 }.to_string(),
+            "nothing to escape at the end of row 2"
+        );
+
+        // escaped dots, slashes and backslashes match literally
+        let grid = Grid::parse("a.b/\n./\\.\n").unwrap();
+        assert_eq!(
+            grid.find_template(&Template::parse(r"\.\/").unwrap(), Symmetry::None),
+            [TemplateMatch {
+                top_left: (1, 0),
+                orientation: 0
+            }]
+        );
+        assert_eq!(
+            grid.find_template(&Template::parse(r"b\/ / \\.").unwrap(), Symmetry::None),
+            [TemplateMatch {
+                top_left: (0, 2),
+                orientation: 0
+            }]
+        );
+
+        const EXAMPLE: &str = "MMMSXXMASM\nMSAMXMSMSA\nAMXSXMAAMM\nMSAMASMSMX\nXMASAMXAMM\n\
+                               XXAMMXXAMA\nSMSMSASXSS\nSAXAMASAAA\nMAMMMXMMMM\nMXMXAXMASX\n";
+        let grid = Grid::parse(EXAMPLE).unwrap();
+        assert_eq!(grid.find_template(&x_mas, Symmetry::Rotations).len(), 9);
+        assert_eq!(grid.find_template(&x_mas, Symmetry::All).len(), 9);
+        assert_eq!(grid.find_template(&x_mas, Symmetry::None).len(), 2);
+    }
+
+    #[test]
+    pub fn parse_test() {
+        const EXAMPLE: &str = "MMMSXXMASM\nMSAMXMSMSA\nAMXSXMAAMM\n";
+        let grid = Grid::parse(EXAMPLE).unwrap();
+        assert_eq!(grid.to_string(), EXAMPLE);
+        assert_eq!((grid.height(), grid.width()), (3, 10));
+
+        for input in [
+            "MMMSXXMASM\r\nMSAMXMSMSA\r\nAMXSXMAAMM\r\n",
+            "MMMSXXMASM  \nMSAMXMSMSA\t\nAMXSXMAAMM",
+            "MMMSXXMASM\nMSAMXMSMSA\nAMXSXMAAMM\n\n \r\n",
+        ] {
+            assert_eq!(
+                Grid::parse(input).unwrap().to_string(),
+                EXAMPLE,
+                "{input:?}"
+            );
+        }
+        assert!(Grid::parse("").unwrap().is_empty());
+        assert!(Grid::parse("\n\r\n").unwrap().is_empty());
+        assert_eq!(Grid::parse("\n\nXMAS\n").unwrap().to_string(), "XMAS\n");
+
+        let error = |kind, line, column| Err(ParseError { kind, line, column });
+        #[rustfmt::skip]
+        let cases = [
+            ("XMAS\nXM\x07S\n", error(ParseErrorKind::InvalidByte(7), 2, 3)),
+            ("XMAS\nX MS\n", error(ParseErrorKind::InvalidByte(b' '), 2, 2)),
+            (" XMAS\n", error(ParseErrorKind::InvalidByte(b' '), 1, 1)),
+            ("XM\rAS\n", error(ParseErrorKind::InvalidByte(b'\r'), 1, 3)),
+            ("XMAS\nXMÄS\n", error(ParseErrorKind::InvalidByte(0xC3), 2, 3)),
+            ("XMAS\nXMA\n", error(ParseErrorKind::RaggedRow { expected: 4 }, 2, 4)),
+            ("XMAS\nXMASX\n", error(ParseErrorKind::RaggedRow { expected: 4 }, 2, 5)),
+            ("XMAS\n\nXMAS\n", error(ParseErrorKind::RaggedRow { expected: 4 }, 2, 1)),
+            ("XMAS\r\n\r\nXMAS\r\n", error(ParseErrorKind::RaggedRow { expected: 4 }, 2, 1)),
+            ("XMAS\n\nX MS\n", error(ParseErrorKind::RaggedRow { expected: 4 }, 2, 1)),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                Grid::parse(input).map(|grid| grid.to_string()),
+                expected,
+                "{input:?}"
+            );
+        }
+
+        assert_eq!(
+            Grid::parse("XMAS\nXM\x07S").unwrap_err().to_string(),
+            "invalid byte 0x07 at line 2, column 3"
+        );
+        assert_eq!(
+            Grid::parse("XMAS\nXMA").unwrap_err().to_string(),
+            "row is not 4 letters long at line 2, column 4"
+        );
+    }
+
+    #[test]
+    pub fn bitboard_test() {
+        const EXAMPLE: &str = "MMMSXXMASM\nMSAMXMSMSA\nAMXSXMAAMM\nMSAMASMSMX\nXMASAMXAMM\n\
+                               XXAMMXXAMA\nSMSMSASXSS\nSAXAMASAAA\nMAMMMXMMMM\nMXMXAXMASX\n";
+        let grid = Grid::parse(EXAMPLE).unwrap();
+        assert_eq!(part1_bitboard(&grid), 18);
+        assert_eq!(part2_bitboard(&grid), 9);
+
+        let mut rng = TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let mut next = move || rng.random::<u64>();
+        for (height, width) in [
+            (1, 1),
+            (1, 4),
+            (4, 1),
+            (3, 3),
+            (7, 63),
+            (64, 64),
+            (5, 65),
+            (70, 130),
+            (130, 70),
+            (200, 200),
+        ] {
+            let input: String = (0..height)
+                .map(|_| {
+                    let row: String = (0..width)
+                        .map(|_| char::from(b"XMASX.MA"[(next() % 8) as usize]))
+                        .collect();
+                    row + "\n"
+                })
+                .collect();
+            let grid = Grid::parse(&input).unwrap();
+            assert_eq!(part1_bitboard(&grid), part1(&grid), "{height}x{width}");
+            assert_eq!(part2_bitboard(&grid), part2(&grid), "{height}x{width}");
+        }
+        assert_eq!(part1_bitboard(&Grid::parse("").unwrap()), 0);
+    }
+
+    #[test]
+    pub fn render_test() {
+        const SMALL: &str = "..X...\n.SAMX.\n.A..A.\nXMAS.S\n.X....\n";
+        let grid = Grid::parse(SMALL).unwrap();
+        let matches = part1_matches(&grid);
+        assert_eq!(matches.len(), 4);
+        assert_eq!(
+            grid.render(matches.iter().flat_map(WordMatch::cells)),
+            SMALL
+        );
+
+        const EXAMPLE: &str = "MMMSXXMASM\nMSAMXMSMSA\nAMXSXMAAMM\nMSAMASMSMX\nXMASAMXAMM\n\
+                               XXAMMXXAMA\nSMSMSASXSS\nSAXAMASAAA\nMAMMMXMMMM\nMXMXAXMASX\n";
+        let grid = Grid::parse(EXAMPLE).unwrap();
+
+        const XMAS_DIAGRAM: &str = "\
+....XXMAS.
+.SAMXMS...
+...S..A...
+..A.A.MS.X
+XMASAMX.MM
+X.....XA.A
+S.S.S.S.SS
+.A.A.A.A.A
+..M.M.M.MM
+.X.X.XMASX
+";
+        let matches = part1_matches(&grid);
+        assert_eq!(
+            grid.render(matches.iter().flat_map(WordMatch::cells)),
+            XMAS_DIAGRAM
+        );
+
+        const X_MAS_DIAGRAM: &str = "\
+.M.S......
+..A..MSMS.
+.M.S.MAA..
+..A.ASMSM.
+.M.S.M....
+..........
+S.S.S.S.S.
+.A.A.A.A..
+M.M.M.M.M.
+..........
+";
+        let orientations = Template::parse(X_MAS)
+            .unwrap()
+            .orientations(Symmetry::Rotations);
+        let matches = part2_matches(&grid);
+        let cells = matches
+            .iter()
+            .flat_map(|found| orientations[found.orientation].cells(found.top_left));
+        assert_eq!(grid.render(cells), X_MAS_DIAGRAM);
+
+        let small = Grid::parse("XMAS\nM..A\n").unwrap();
+        assert_eq!(
+            small.render_words_ansi(&part1_matches(&small)),
+            "\x1b[1;33mX\x1b[0m\x1b[1;33mM\x1b[0m\x1b[1;33mA\x1b[0m\x1b[1;33mS\x1b[0m\n\
+             \x1b[2mM\x1b[0m\x1b[2m.\x1b[0m\x1b[2m.\x1b[0m\x1b[2mA\x1b[0m\n"
+        );
+        assert_eq!(Grid::parse("").unwrap().render([]), "");
+
+        // Each letter of the example as its colour's index in the palette, where later matches
+        // paint over earlier ones: the direction in the order of `Direction::ALL` for XMAS, and
+        // the orientation for X-MAS.
+        let rendered = grid.render_words_ansi(&part1_matches(&grid));
+        assert_eq!(strip_ansi(&rendered), EXAMPLE);
+        const XMAS_COLOURS: &str = "\
+....72222.
+.333370...
+...4..0...
+..5.4.07.6
+2523343.61
+5.....46.1
+4.4.5.4.50
+.4.4.5.4.0
+..4.4.5.40
+.5.5.52224
+";
+        assert_eq!(colours(&rendered), XMAS_COLOURS);
+
+        let rendered = grid.render_template_ansi(
+            &Template::parse(X_MAS).unwrap(),
+            Symmetry::Rotations,
+            &part2_matches(&grid),
+        );
+        assert_eq!(strip_ansi(&rendered), EXAMPLE);
+        const X_MAS_COLOURS: &str = "\
+.0.0......
+..0..1313.
+.0.2.213..
+..0.21313.
+.0.2.2....
+..........
+3.3.3.3.3.
+.3.3.3.3..
+3.3.3.3.3.
+..........
+";
+        assert_eq!(colours(&rendered), X_MAS_COLOURS);
+    }
+
+    /// The letters of an ANSI rendering.
+    fn strip_ansi(rendered: &str) -> String {
+        let mut stripped = String::new();
+        let mut escaped = false;
+        for c in rendered.chars() {
+            match c {
+                '\x1b' => escaped = true,
+                'm' if escaped => escaped = false,
+                _ if !escaped => stripped.push(c),
+                _ => {}
+            }
+        }
+        stripped
+    }
+
+    /// An ANSI rendering with each letter replaced by the index of its colour in `PALETTE`, or a
+    /// dot if it is dimmed.
+    fn colours(rendered: &str) -> String {
+        rendered
+            .split_inclusive('\n')
+            .map(|line| {
+                let mut colours: String = line
+                    .trim_end_matches('\n')
+                    .split_terminator("\x1b[0m")
+                    .map(|cell| match cell.strip_prefix("\x1b[1;") {
+                        Some(cell) => {
+                            let code: u8 = cell[..cell.find('m').unwrap()].parse().unwrap();
+                            let index = PALETTE.iter().position(|&c| c == code).unwrap();
+                            char::from(b'0' + index as u8)
+                        }
+                        None => '.',
+                    })
+                    .collect();
+                colours.push('\n');
+                colours
+            })
+            .collect()
     }
 }
 
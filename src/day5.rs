@@ -1,19 +1,16 @@
 use std::{
-    cmp::Ordering,
-    collections::{hash_map::Entry, HashMap, HashSet},
+    cmp::Reverse,
+    collections::{hash_map::Entry, BinaryHeap, HashMap, HashSet},
+    fmt::Display,
 };
 
 use aoc_runner_derive::aoc;
 #[aoc(day5, part1)]
-pub fn part1(input: &str) -> u32 {
-    let (ordering, updates) = input.split_at(input.find("\n\n").unwrap());
-    let ordering = PageOrdering::parse(ordering);
-    let updates: Vec<Vec<u32>> = updates
-        .trim()
-        .lines()
-        .map(|line| line.split(",").map(|p| p.parse::<u32>().unwrap()).collect())
-        .filter(|s: &Vec<u32>| s.is_sorted_by(|a, b| ordering.sorted(a, b)))
-        .collect();
+pub fn part1(input: &str) -> Result<u32, ParseError> {
+    let (ordering, updates) = parse_input(input)?;
+    let updates = updates
+        .into_iter()
+        .filter(|update| ordering.is_ordered(update));
 
     let mut middle_sum = 0;
 
@@ -21,40 +18,325 @@ pub fn part1(input: &str) -> u32 {
         let midpoint = update.len() / 2;
         middle_sum += update[midpoint];
     }
-    middle_sum
+    Ok(middle_sum)
 }
 
+/// Sorts each update that breaks a rule with `PageOrdering::order`. Where the rules allow more than
+/// one order, that takes the smallest page first, so the middle page is that of one valid order
+/// among several. `Order::ambiguous` tells which updates those are.
 #[aoc(day5, part2)]
-pub fn part2(input: &str) -> u32 {
-    let (ordering, updates) = input.split_at(input.find("\n\n").unwrap());
-    let ordering = PageOrdering::parse(ordering);
-    let updates: Vec<Vec<u32>> = updates
-        .trim()
-        .lines()
-        .map(|line| line.split(",").map(|p| p.parse::<u32>().unwrap()).collect())
-        .filter(|s: &Vec<u32>| !s.is_sorted_by(|a, b| ordering.sorted(a, b)))
-        .collect();
+pub fn part2(input: &str) -> Result<u32, PuzzleError> {
+    let (ordering, updates) = parse_input(input)?;
+    let updates = updates
+        .into_iter()
+        .filter(|update| !ordering.is_ordered(update));
 
     let mut middle_sum = 0;
 
-    for mut update in updates {
-        update.sort_by(|a, b| ordering.sort(a, b));
-        let midpoint = update.len() / 2;
-        middle_sum += update[midpoint];
+    for update in updates {
+        let order = ordering.order(&update)?;
+        let midpoint = order.pages.len() / 2;
+        middle_sum += order.pages[midpoint];
+    }
+    Ok(middle_sum)
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ParseErrorKind {
+    /// A rule without a `|` between its pages.
+    MissingSeparator,
+    /// A page that isn't a number.
+    InvalidPage,
+}
+
+/// An error in the rules or updates, pointing at the offending line (1-based) and column (1-based).
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self.kind {
+            ParseErrorKind::MissingSeparator => "expected a `|` between two pages",
+            ParseErrorKind::InvalidPage => "invalid page",
+        };
+
+        write!(f, "{reason} at line {}, column {}", self.line, self.column)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Why a puzzle input has no answer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PuzzleError {
+    Parse(ParseError),
+    Cycle(CycleError),
+}
+
+impl From<ParseError> for PuzzleError {
+    fn from(error: ParseError) -> Self {
+        PuzzleError::Parse(error)
+    }
+}
+
+impl From<CycleError> for PuzzleError {
+    fn from(error: CycleError) -> Self {
+        PuzzleError::Cycle(error)
+    }
+}
+
+impl Display for PuzzleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PuzzleError::Parse(error) => error.fmt(f),
+            PuzzleError::Cycle(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for PuzzleError {}
+
+/// Parses `field`, a part of `line`, as a page.
+fn parse_page(line_number: usize, line: &str, field: &str) -> Result<u32, ParseError> {
+    let field = field.trim();
+    field.parse().map_err(|_| ParseError {
+        kind: ParseErrorKind::InvalidPage,
+        line: line_number,
+        column: line[..field.as_ptr() as usize - line.as_ptr() as usize]
+            .chars()
+            .count()
+            + 1,
+    })
+}
+
+/// Parses a `before|after` rule, with any whitespace around the pages.
+fn parse_rule(line_number: usize, line: &str) -> Result<(u32, u32), ParseError> {
+    let Some((before, after)) = line.split_once('|') else {
+        return Err(ParseError {
+            kind: ParseErrorKind::MissingSeparator,
+            line: line_number,
+            column: line.trim_end().chars().count() + 1,
+        });
+    };
+    Ok((
+        parse_page(line_number, line, before)?,
+        parse_page(line_number, line, after)?,
+    ))
+}
+
+/// Parses an update of comma separated pages, with any whitespace around the pages.
+fn parse_update(line_number: usize, line: &str) -> Result<Vec<u32>, ParseError> {
+    line.split(',')
+        .map(|field| parse_page(line_number, line, field))
+        .collect()
+}
+
+/// The pages of an update in an order that follows every rule between them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Order {
+    pub pages: Vec<u32>,
+    /// Two pages that no chain of rules puts in order, if there is more than one valid order.
+    /// The smaller page was put first.
+    pub ambiguous: Option<(u32, u32)>,
+}
+
+impl Order {
+    pub fn is_unique(&self) -> bool {
+        self.ambiguous.is_none()
+    }
+}
+
+/// Positions in an update in topological order.
+struct Sorted {
+    indices: Vec<usize>,
+    /// The first two positions that could have gone either way.
+    ambiguous: Option<(usize, usize)>,
+}
+
+/// The rules between the pages of an update go round in a circle, so they can't be ordered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CycleError {
+    /// The pages in the cycle, each of which must come before the next, and the last before the
+    /// first.
+    pub pages: Vec<u32>,
+}
+
+impl Display for CycleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "rules form a cycle")?;
+        let Some(first) = self.pages.first() else {
+            return Ok(());
+        };
+        write!(f, ": ")?;
+        for page in &self.pages {
+            write!(f, "{page} -> ")?;
+        }
+        write!(f, "{first}")
+    }
+}
+
+impl std::error::Error for CycleError {}
+
+/// Problems found in a set of rules by `PageOrdering::lint`, each sorted.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Lint {
+    /// Pairs of pages with rules both ways round, smaller page first.
+    pub contradictions: Vec<(u32, u32)>,
+    /// Rules that follow from a chain of other rules.
+    pub redundant: Vec<(u32, u32)>,
+    /// Pages in updates that no rule mentions.
+    pub unruled: Vec<u32>,
+}
+
+impl Lint {
+    pub fn is_clean(&self) -> bool {
+        self.contradictions.is_empty() && self.redundant.is_empty() && self.unruled.is_empty()
+    }
+}
+
+/// A rule an update breaks by having `after` before `before`, at positions counted from 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Violation {
+    pub before: u32,
+    pub after: u32,
+    pub before_position: usize,
+    pub after_position: usize,
+}
+
+/// What is wrong with an update and how to fix it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnosis {
+    pub violations: Vec<Violation>,
+    /// The fewest pages that need moving elsewhere in the update for it to follow the rules.
+    pub moves: usize,
+    /// The update with that many pages moved.
+    pub corrected: Vec<u32>,
+}
+
+impl Diagnosis {
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// The largest set of `0..n` with no two related by `less`, a strict partial order, in
+/// ascending order. By Kőnig's theorem its size is `n` less a maximum matching between `less`
+/// as a bipartite graph, whose minimum vertex cover the antichain avoids.
+fn max_antichain(n: usize, less: impl Fn(usize, usize) -> bool) -> Vec<usize> {
+    fn augment(
+        u: usize,
+        n: usize,
+        less: &impl Fn(usize, usize) -> bool,
+        seen: &mut [bool],
+        matched: &mut [Option<usize>],
+    ) -> bool {
+        for v in 0..n {
+            if less(u, v) && !seen[v] {
+                seen[v] = true;
+                if matched[v].is_none_or(|w| augment(w, n, less, seen, matched)) {
+                    matched[v] = Some(u);
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    // `matched[v]`: the left vertex matched to right vertex `v`
+    let mut matched = vec![None; n];
+    for u in 0..n {
+        augment(u, n, &less, &mut vec![false; n], &mut matched);
+    }
+
+    // Vertices reachable by alternating paths from unmatched left vertices.
+    let mut left = vec![true; n];
+    for &u in matched.iter().flatten() {
+        left[u] = false;
     }
-    middle_sum
+    let mut right = vec![false; n];
+    let mut stack: Vec<usize> = (0..n).filter(|&u| left[u]).collect();
+    while let Some(u) = stack.pop() {
+        for v in 0..n {
+            if less(u, v) && !right[v] {
+                right[v] = true;
+                if let Some(w) = matched[v].filter(|&w| !left[w]) {
+                    left[w] = true;
+                    stack.push(w);
+                }
+            }
+        }
+    }
+
+    (0..n).filter(|&x| left[x] && !right[x]).collect()
+}
+
+/// Splits the puzzle input into its rules and the updates after the first blank line, tolerating
+/// CRLF line endings and blank lines before the rules and among the updates. Without a blank line,
+/// it is all rules.
+fn parse_input(input: &str) -> Result<(PageOrdering, Vec<Vec<u32>>), ParseError> {
+    let blank = |(_, line): &(usize, &str)| line.trim().is_empty();
+    let mut lines = input
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line))
+        .skip_while(blank);
+
+    let ordering = PageOrdering::from_lines(lines.by_ref().take_while(|line| !blank(line)))?;
+    let updates = lines
+        .filter(|line| !blank(line))
+        .map(|(line_number, line)| parse_update(line_number, line))
+        .collect::<Result<_, _>>()?;
+    Ok((ordering, updates))
 }
+
+/// Every problem with the rules in the puzzle input, and with the pages its updates use.
+pub fn lint_input(input: &str) -> Result<Lint, ParseError> {
+    let (ordering, updates) = parse_input(input)?;
+    Ok(ordering.lint(&updates))
+}
+
+/// An update that breaks a rule, with what is wrong with it, or the cycle in the rules between its
+/// pages that leaves it with no valid order.
+pub type Rejected = (Vec<u32>, Result<Diagnosis, CycleError>);
+
+/// Every update that breaks a rule.
+pub fn diagnose_updates(input: &str) -> Result<Vec<Rejected>, ParseError> {
+    let (ordering, updates) = parse_input(input)?;
+    let mut rejected = Vec::new();
+    for update in updates {
+        let diagnosis = ordering.diagnose(&update);
+        if !diagnosis.as_ref().is_ok_and(Diagnosis::is_valid) {
+            rejected.push((update, diagnosis));
+        }
+    }
+    Ok(rejected)
+}
+
 pub struct PageOrdering {
     orders: HashMap<u32, HashSet<u32>>,
 }
 
 impl PageOrdering {
-    pub fn parse(input: &str) -> Self {
+    /// Parses one `before|after` rule per line, skipping blank lines.
+    pub fn parse(input: &str) -> Result<Self, ParseError> {
+        Self::from_lines(
+            input
+                .lines()
+                .enumerate()
+                .map(|(index, line)| (index + 1, line))
+                .filter(|(_, line)| !line.trim().is_empty()),
+        )
+    }
+
+    /// Parses rules from `(line number, line)` pairs.
+    fn from_lines<'a>(lines: impl Iterator<Item = (usize, &'a str)>) -> Result<Self, ParseError> {
         let mut orders: HashMap<u32, HashSet<u32>> = HashMap::new();
-        for line in input.lines() {
-            let mut split = line.split("|");
-            let before = split.next().and_then(|s| s.parse::<u32>().ok()).unwrap();
-            let after = split.next().and_then(|s| s.parse::<u32>().ok()).unwrap();
+        for (line_number, line) in lines {
+            let (before, after) = parse_rule(line_number, line)?;
             match orders.entry(before) {
                 Entry::Occupied(mut occupied_entry) => {
                     occupied_entry.get_mut().insert(after);
@@ -66,77 +348,267 @@ impl PageOrdering {
                 }
             };
         }
-        PageOrdering { orders }
+        Ok(PageOrdering { orders })
     }
 
-    pub fn sorted(&self, a: &u32, b: &u32) -> bool {
-        // let orderings_a = self.orders.get(a);
-        let orderings_b = self.orders.get(b);
+    /// Every rule as `(before, after)`, sorted.
+    pub fn rules(&self) -> Vec<(u32, u32)> {
+        let mut rules: Vec<_> = self
+            .orders
+            .iter()
+            .flat_map(|(&before, afters)| afters.iter().map(move |&after| (before, after)))
+            .collect();
+        rules.sort_unstable();
+        rules
+    }
 
-        if orderings_b.is_none() {
-            // Order free
-            return true;
+    /// Pages reachable from `page` by following one or more rules, never taking the rule
+    /// `skip` directly.
+    fn reachable(&self, page: u32, skip: Option<(u32, u32)>) -> HashSet<u32> {
+        let mut seen = HashSet::new();
+        let mut stack = vec![page];
+        while let Some(before) = stack.pop() {
+            let Some(afters) = self.orders.get(&before) else {
+                continue;
+            };
+            for &after in afters {
+                if skip != Some((before, after)) && seen.insert(after) {
+                    stack.push(after);
+                }
+            }
         }
+        seen
+    }
 
-        if let Some(order) = orderings_b {
-            // Everything in the b set has to be to the left of a, so
-            // if it contains a then its not ordered.
-            if order.contains(a) {
-                return false;
-            }
+    /// Whether a chain of rules requires `a` to come before `b`. A page is only required before
+    /// itself when it is part of a cycle.
+    pub fn requires_before(&self, a: u32, b: u32) -> bool {
+        self.reachable(a, None).contains(&b)
+    }
+
+    /// Every page each page is required to come before, through any chain of rules.
+    pub fn transitive_closure(&self) -> HashMap<u32, HashSet<u32>> {
+        self.orders
+            .keys()
+            .map(|&page| (page, self.reachable(page, None)))
+            .collect()
+    }
+
+    /// Pairs of pages with a rule each way round.
+    pub fn contradictions(&self) -> Vec<(u32, u32)> {
+        self.rules()
+            .into_iter()
+            .filter(|&(before, after)| {
+                before < after && self.orders.get(&after).is_some_and(|a| a.contains(&before))
+            })
+            .collect()
+    }
+
+    /// Rules whose order also follows from a chain of other rules. Where rules form cycles
+    /// this includes rules that only follow by going around the cycle.
+    pub fn redundant_rules(&self) -> Vec<(u32, u32)> {
+        self.rules()
+            .into_iter()
+            .filter(|&(before, after)| {
+                self.reachable(before, Some((before, after)))
+                    .contains(&after)
+            })
+            .collect()
+    }
+
+    /// Pages in `updates` that no rule mentions, so they can go anywhere.
+    pub fn unruled_pages(&self, updates: &[Vec<u32>]) -> Vec<u32> {
+        let ruled: HashSet<u32> = self.rules().into_iter().flat_map(|(a, b)| [a, b]).collect();
+        let mut unruled: Vec<u32> = updates
+            .iter()
+            .flatten()
+            .copied()
+            .filter(|page| !ruled.contains(page))
+            .collect();
+        unruled.sort_unstable();
+        unruled.dedup();
+        unruled
+    }
+
+    /// Every problem with the rules, and with the pages `updates` use.
+    pub fn lint(&self, updates: &[Vec<u32>]) -> Lint {
+        Lint {
+            contradictions: self.contradictions(),
+            redundant: self.redundant_rules(),
+            unruled: self.unruled_pages(updates),
         }
+    }
 
-        // if let Some(order) = orderings_a {
-        //     // Everything in the a set has to be to the left of a, so
-        //     // if it contains a then its not ordered.
-        //     if order.contains(a) {
-        //         return false;
-        //     }
-        // }
+    /// The rules between the pages of `update`, as the positions each position must come before.
+    fn subgraph(&self, update: &[u32]) -> Vec<Vec<usize>> {
+        update
+            .iter()
+            .enumerate()
+            .map(|(i, before)| {
+                let Some(afters) = self.orders.get(before) else {
+                    return Vec::new();
+                };
+                (0..update.len())
+                    .filter(|&j| i != j && afters.contains(&update[j]))
+                    .collect()
+            })
+            .collect()
+    }
 
-        return true;
+    /// Whether `update` follows every rule between its pages, not only those between neighbours.
+    pub fn is_ordered(&self, update: &[u32]) -> bool {
+        self.subgraph(update)
+            .iter()
+            .enumerate()
+            .all(|(i, afters)| afters.iter().all(|&j| j > i))
     }
 
-    pub fn sort(&self, a: &u32, b: &u32) -> Ordering {
-        let orderings_a = self.orders.get(a);
-        let orderings_b = self.orders.get(b);
+    /// Sorts `update` topologically by the rules between its pages, taking the smallest page
+    /// whenever several could come next.
+    pub fn order(&self, update: &[u32]) -> Result<Order, CycleError> {
+        let Sorted { indices, ambiguous } = self.sort_indices(update, &[], |i| update[i])?;
+        Ok(Order {
+            pages: indices.iter().map(|&i| update[i]).collect(),
+            ambiguous: ambiguous.map(|(a, b)| (update[a], update[b])),
+        })
+    }
 
-        if orderings_a.is_none() && orderings_b.is_none() {
-            // Order free
-            return Ordering::Equal;
+    /// Sorts the positions in `update` topologically by the rules between its pages and the
+    /// `extra` edges between positions, taking the one with the smallest `key` whenever several
+    /// could come next.
+    fn sort_indices<K: Ord>(
+        &self,
+        update: &[u32],
+        extra: &[(usize, usize)],
+        key: impl Fn(usize) -> K,
+    ) -> Result<Sorted, CycleError> {
+        let mut successors = self.subgraph(update);
+        for &(i, j) in extra {
+            successors[i].push(j);
+        }
+        let mut predecessors = vec![0; update.len()];
+        for &j in successors.iter().flatten() {
+            predecessors[j] += 1;
         }
 
-        if let Some(order) = orderings_b {
-            // Everything in the b set has to be to the left of a, so
-            // if it contains a then its not ordered.
-            if order.contains(a) {
-                return Ordering::Greater;
+        let mut ready: BinaryHeap<_> = (0..update.len())
+            .filter(|&i| predecessors[i] == 0)
+            .map(|i| Reverse((key(i), i)))
+            .collect();
+        let mut indices = Vec::with_capacity(update.len());
+        let mut ambiguous = None;
+        while let Some(Reverse((_, i))) = ready.pop() {
+            if let (None, Some(Reverse((_, other)))) = (ambiguous, ready.peek()) {
+                ambiguous = Some((i, *other));
             }
+            indices.push(i);
+            for &j in &successors[i] {
+                predecessors[j] -= 1;
+                if predecessors[j] == 0 {
+                    ready.push(Reverse((key(j), j)));
+                }
+            }
+        }
+
+        if indices.len() < update.len() {
+            return Err(CycleError {
+                pages: Self::find_cycle(update, &successors, &predecessors),
+            });
         }
 
-        if let Some(order) = orderings_a {
-            // Everything in the b set has to be to the left of a, so
-            // if it contains a then its not ordered.
-            if order.contains(b) {
-                return Ordering::Less;
+        Ok(Sorted { indices, ambiguous })
+    }
+
+    /// Every rule broken by `update`, how few pages need moving to fix it, and the update with
+    /// them moved, keeping the rest where they were relative to each other.
+    pub fn diagnose(&self, update: &[u32]) -> Result<Diagnosis, CycleError> {
+        let n = update.len();
+        let rule = |i: usize, j: usize| {
+            i != j
+                && self
+                    .orders
+                    .get(&update[i])
+                    .is_some_and(|a| a.contains(&update[j]))
+        };
+
+        // The same rules `is_ordered` checks, so an update is valid exactly when it is ordered.
+        let mut violations: Vec<Violation> = self
+            .subgraph(update)
+            .iter()
+            .enumerate()
+            .flat_map(|(i, afters)| {
+                afters
+                    .iter()
+                    .filter(move |&&j| j < i)
+                    .map(move |&j| Violation {
+                        before: update[i],
+                        after: update[j],
+                        before_position: i,
+                        after_position: j,
+                    })
+            })
+            .collect();
+        violations.sort_unstable_by_key(|v| (v.after_position, v.before_position));
+
+        // Check for cycles before relying on the closure being a partial order.
+        self.sort_indices(update, &[], |i| i)?;
+
+        // `required[i][j]`: a chain of rules through the update puts `update[i]` before `update[j]`.
+        let mut required: Vec<Vec<bool>> = (0..n)
+            .map(|i| (0..n).map(|j| rule(i, j)).collect())
+            .collect();
+        for k in 0..n {
+            let via = required[k].clone();
+            for row in required.iter_mut().filter(|row| row[k]) {
+                for (required, &via) in row.iter_mut().zip(&via) {
+                    *required |= via;
+                }
             }
         }
 
-        // if let Some(order) = orderings_a {
-        //     // Everything in the a set has to be to the left of a, so
-        //     // if it contains a then its not ordered.
-        //     if order.contains(a) {
-        //         return false;
-        //     }
-        // }
+        // Pages can stay put together unless one is required before another it comes after.
+        // Such reversed pairs form a partial order, and the most pages that can stay are its
+        // largest antichain.
+        let kept = max_antichain(n, |i, j| i > j && required[i][j]);
+        let chain: Vec<(usize, usize)> = kept.windows(2).map(|w| (w[0], w[1])).collect();
+        let sorted = self.sort_indices(update, &chain, |i| i)?;
+
+        Ok(Diagnosis {
+            violations,
+            moves: n - kept.len(),
+            corrected: sorted.indices.iter().map(|&i| update[i]).collect(),
+        })
+    }
+
+    /// Follows unsorted pages back through their predecessors until one repeats. Every page left
+    /// unsorted has an unsorted predecessor, so this always finds a cycle.
+    fn find_cycle(update: &[u32], successors: &[Vec<usize>], predecessors: &[u32]) -> Vec<u32> {
+        let mut path: Vec<usize> = Vec::new();
+        let mut current = (0..update.len()).find(|&i| predecessors[i] > 0).unwrap();
+        while !path.contains(&current) {
+            path.push(current);
+            current = (0..update.len())
+                .find(|&i| predecessors[i] > 0 && successors[i].contains(&current))
+                .unwrap();
+        }
 
-        return Ordering::Equal;
+        let start = path.iter().position(|&i| i == current).unwrap();
+        path[start..].iter().rev().map(|&i| update[i]).collect()
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::day5::part2;
+    use crate::day5::{
+        diagnose_updates, lint_input, part2, CycleError, Lint, Order, ParseError, ParseErrorKind,
+        PuzzleError, Violation,
+    };
+
+    use proptest::{
+        prelude::RngExt,
+        test_runner::{RngAlgorithm, TestRng},
+    };
+    use std::collections::HashSet;
 
     use super::{part1, PageOrdering};
 
@@ -164,15 +636,16 @@ mod test {
 47|29
 75|13
 53|13"#,
-        );
+        )
+        .unwrap();
 
-        assert!([75, 47, 61, 53, 29].is_sorted_by(|a, b| ordering.sorted(a, b)));
-        assert!([97, 61, 53, 29, 13].is_sorted_by(|a, b| ordering.sorted(a, b)));
-        assert!([75, 29, 13].is_sorted_by(|a, b| ordering.sorted(a, b)));
+        assert!(ordering.is_ordered(&[75, 47, 61, 53, 29]));
+        assert!(ordering.is_ordered(&[97, 61, 53, 29, 13]));
+        assert!(ordering.is_ordered(&[75, 29, 13]));
 
-        assert!(![75, 97, 47, 61, 53].is_sorted_by(|a, b| ordering.sorted(a, b)));
-        assert!(![61, 13, 29].is_sorted_by(|a, b| ordering.sorted(a, b)));
-        assert!(![97, 13, 75, 29, 47].is_sorted_by(|a, b| ordering.sorted(a, b)));
+        assert!(!ordering.is_ordered(&[75, 97, 47, 61, 53]));
+        assert!(!ordering.is_ordered(&[61, 13, 29]));
+        assert!(!ordering.is_ordered(&[97, 13, 75, 29, 47]));
     }
 
     #[test]
@@ -208,7 +681,7 @@ mod test {
 97,13,75,29,47"#,
         );
 
-        assert_eq!(ordering, 143);
+        assert_eq!(ordering, Ok(143));
     }
 
     #[test]
@@ -244,6 +717,479 @@ mod test {
 97,13,75,29,47"#,
         );
 
-        assert_eq!(ordering, 123);
+        assert_eq!(ordering, Ok(123));
+    }
+
+    #[test]
+    fn sparse_rules_test() {
+        // 3 and 1 aren't neighbours, but 3 still comes after 1
+        let ordering = PageOrdering::parse("1|3").unwrap();
+        assert!(!ordering.is_ordered(&[3, 2, 1]));
+        assert!(ordering.is_ordered(&[1, 2, 3]));
+        assert!(ordering.is_ordered(&[2, 4]));
+        assert!(ordering.is_ordered(&[]));
+
+        assert_eq!(part1("1|3\n\n3,2,1"), Ok(0));
+        assert_eq!(part2("1|3\n\n3,2,1"), Ok(2));
+        assert_eq!(part1("1|3\n\n3,2,1\n1,4,3\n5,6,7"), Ok(10));
+        assert_eq!(part2("1|3\n\n3,2,1\n1,4,3\n5,6,7"), Ok(2));
+    }
+
+    #[test]
+    fn order_test() {
+        let ordering = PageOrdering::parse(
+            r#"47|53
+97|13
+97|61
+97|47
+75|29
+61|13
+75|53
+29|13
+97|29
+53|29
+61|53
+97|53
+61|29
+47|13
+75|47
+97|75
+47|61
+75|61
+47|29
+75|13
+53|13"#,
+        )
+        .unwrap();
+
+        let unique = |pages: Vec<u32>| {
+            Ok(Order {
+                pages,
+                ambiguous: None,
+            })
+        };
+        assert_eq!(
+            ordering.order(&[75, 97, 47, 61, 53]),
+            unique(vec![97, 75, 47, 61, 53])
+        );
+        assert_eq!(ordering.order(&[61, 13, 29]), unique(vec![61, 29, 13]));
+        assert_eq!(
+            ordering.order(&[97, 13, 75, 29, 47]),
+            unique(vec![97, 75, 47, 29, 13])
+        );
+        assert_eq!(ordering.order(&[]), unique(vec![]));
+
+        // 53 and 13 are unrelated to 99, and nothing puts 99 anywhere
+        let order = ordering.order(&[13, 99, 53]).unwrap();
+        assert_eq!(order.pages, [53, 13, 99]);
+        assert_eq!(order.ambiguous, Some((53, 99)));
+        assert!(!order.is_unique());
+
+        // rules only order pages through the pages in the update itself
+        let order = ordering.order(&[13, 75]).unwrap();
+        assert_eq!(order.pages, [75, 13]);
+        assert!(order.is_unique());
+
+        let cyclic = PageOrdering::parse("1|2\n2|3\n3|1\n3|4\n5|1").unwrap();
+        let error = cyclic.order(&[4, 5, 3, 2, 1]).unwrap_err();
+        assert_eq!(
+            error,
+            CycleError {
+                pages: vec![1, 2, 3]
+            }
+        );
+        assert_eq!(error.to_string(), "rules form a cycle: 1 -> 2 -> 3 -> 1");
+        assert_eq!(
+            cyclic.order(&[2, 1, 5]).map(|order| order.pages),
+            Ok(vec![5, 1, 2])
+        );
+
+        let two = PageOrdering::parse("7|8\n8|7").unwrap();
+        assert_eq!(two.order(&[7, 8]).unwrap_err().pages.len(), 2);
+        assert!(part2("7|8\n8|7\n\n7,8").is_err());
+    }
+
+    #[test]
+    fn parse_error_test() {
+        // CRLF line endings, surrounding whitespace and stray blank lines are all fine
+        assert_eq!(
+            lint_input("1|2\r\n2|1\r\n\r\n1,2\r\n"),
+            lint_input("1|2\n2|1\n\n1,2\n")
+        );
+        assert_eq!(part1("\n 1 | 3 \n\n\n 1 , 2 , 3 \n\n"), Ok(2));
+        assert_eq!(part1("1|3\r\n\r\n3,2,1\r\n1,2,3\r\n"), Ok(2));
+
+        assert_eq!(
+            lint_input("1|2\n2|x\n"),
+            Err(ParseError {
+                kind: ParseErrorKind::InvalidPage,
+                line: 2,
+                column: 3,
+            })
+        );
+        assert_eq!(
+            part1("1|2\n12\n\n1,2"),
+            Err(ParseError {
+                kind: ParseErrorKind::MissingSeparator,
+                line: 2,
+                column: 3,
+            })
+        );
+        // line numbers count the rules and the blank line before the updates
+        assert_eq!(
+            part2("1|2\r\n\r\n2,1\r\n2,,1\r\n"),
+            Err(PuzzleError::Parse(ParseError {
+                kind: ParseErrorKind::InvalidPage,
+                line: 4,
+                column: 3,
+            }))
+        );
+        assert_eq!(
+            part1("1|2\n2|\n").unwrap_err().to_string(),
+            "invalid page at line 2, column 3"
+        );
+        assert_eq!(
+            part1("1 2").unwrap_err().to_string(),
+            "expected a `|` between two pages at line 1, column 4"
+        );
+    }
+
+    #[test]
+    fn lint_test() {
+        let ordering = PageOrdering::parse("1|2\n2|3\n1|3\n3|4\n4|3\n5|6\n1|6").unwrap();
+        assert_eq!(
+            ordering.rules(),
+            [(1, 2), (1, 3), (1, 6), (2, 3), (3, 4), (4, 3), (5, 6)]
+        );
+        assert_eq!(ordering.contradictions(), [(3, 4)]);
+        assert_eq!(ordering.redundant_rules(), [(1, 3)]);
+
+        assert!(ordering.requires_before(1, 4));
+        assert!(ordering.requires_before(2, 4));
+        assert!(!ordering.requires_before(4, 1));
+        assert!(!ordering.requires_before(5, 1));
+        assert!(!ordering.requires_before(1, 1));
+        assert!(ordering.requires_before(3, 3));
+
+        let closure = ordering.transitive_closure();
+        assert_eq!(closure[&1], [2, 3, 4, 6].into());
+        assert_eq!(closure[&4], [3, 4].into());
+        assert_eq!(closure[&5], [6].into());
+        assert!(!closure.contains_key(&6));
+
+        let updates = [vec![1, 2, 7], vec![8, 5, 7], vec![]];
+        assert_eq!(ordering.unruled_pages(&updates), [7, 8]);
+        assert_eq!(
+            ordering.lint(&updates),
+            Lint {
+                contradictions: vec![(3, 4)],
+                redundant: vec![(1, 3)],
+                unruled: vec![7, 8],
+            }
+        );
+
+        let clean = PageOrdering::parse("1|2\n2|3").unwrap();
+        assert!(clean.lint(&[vec![3, 1]]).is_clean());
+
+        assert_eq!(
+            lint_input("1|2\n2|3\n1|3\n3|4\n4|3\n5|6\n1|6\n\n1,2,7\n8,5,7\n"),
+            Ok(ordering.lint(&updates))
+        );
+        // a file with only rules has no updates to check
+        assert_eq!(
+            lint_input("1|2\n2|1"),
+            Ok(Lint {
+                contradictions: vec![(1, 2)],
+                ..Lint::default()
+            })
+        );
+        assert_eq!(
+            CycleError { pages: vec![] }.to_string(),
+            "rules form a cycle"
+        );
+
+        // every rule in the example follows from the others except those between neighbours
+        // in the one order all the pages can go in
+        let example = PageOrdering::parse(
+            r#"47|53
+97|13
+97|61
+97|47
+75|29
+61|13
+75|53
+29|13
+97|29
+53|29
+61|53
+97|53
+61|29
+47|13
+75|47
+97|75
+47|61
+75|61
+47|29
+75|13
+53|13"#,
+        )
+        .unwrap();
+        assert!(example.contradictions().is_empty());
+        assert_eq!(example.rules().len() - example.redundant_rules().len(), 6);
+        assert!(example.requires_before(97, 13));
+    }
+
+    #[test]
+    fn diagnose_test() {
+        let rejected = diagnose_updates(
+            r#"47|53
+97|13
+97|61
+97|47
+75|29
+61|13
+75|53
+29|13
+97|29
+53|29
+61|53
+97|53
+61|29
+47|13
+75|47
+97|75
+47|61
+75|61
+47|29
+75|13
+53|13
+
+75,47,61,53,29
+97,61,53,29,13
+75,29,13
+75,97,47,61,53
+61,13,29
+97,13,75,29,47"#,
+        )
+        .unwrap()
+        .into_iter()
+        .map(|(update, diagnosis)| (update, diagnosis.unwrap()))
+        .collect::<Vec<_>>();
+
+        let violation = |before, after, before_position, after_position| Violation {
+            before,
+            after,
+            before_position,
+            after_position,
+        };
+        assert_eq!(rejected.len(), 3);
+        let (update, diagnosis) = &rejected[0];
+        assert_eq!(update, &[75, 97, 47, 61, 53]);
+        assert_eq!(diagnosis.violations, [violation(97, 75, 1, 0)]);
+        assert_eq!(diagnosis.moves, 1);
+        assert_eq!(diagnosis.corrected, [97, 75, 47, 61, 53]);
+
+        let (_, diagnosis) = &rejected[1];
+        assert_eq!(diagnosis.violations, [violation(29, 13, 2, 1)]);
+        assert_eq!(
+            (diagnosis.moves, &diagnosis.corrected[..]),
+            (1, &[61, 29, 13][..])
+        );
+
+        let (_, diagnosis) = &rejected[2];
+        assert_eq!(
+            diagnosis.violations,
+            [
+                violation(75, 13, 2, 1),
+                violation(29, 13, 3, 1),
+                violation(47, 13, 4, 1),
+                violation(47, 29, 4, 3),
+            ]
+        );
+        assert_eq!(diagnosis.moves, 2);
+        assert_eq!(diagnosis.corrected, [97, 75, 47, 29, 13]);
+
+        // unrelated pages stay where they were
+        let ordering = PageOrdering::parse("1|2\n3|4").unwrap();
+        let diagnosis = ordering.diagnose(&[2, 9, 4, 1, 3]).unwrap();
+        assert_eq!(diagnosis.moves, 2);
+        assert_eq!(diagnosis.violations.len(), 2);
+        assert!(ordering.diagnose(&diagnosis.corrected).unwrap().is_valid());
+        let mut pages = diagnosis.corrected.clone();
+        pages.sort_unstable();
+        assert_eq!(pages, [1, 2, 3, 4, 9]);
+
+        assert!(ordering.diagnose(&[1, 2, 3]).unwrap().is_valid());
+        let cyclic = PageOrdering::parse("1|2\n2|1").unwrap();
+        assert!(cyclic.diagnose(&[1, 2]).is_err());
+    }
+
+    /// The fewest single-page moves that make `update` break no rules, by breadth-first search.
+    fn brute_force_moves(ordering: &PageOrdering, update: &[u32]) -> usize {
+        let mut frontier = vec![update.to_vec()];
+        let mut seen = HashSet::new();
+        for moves in 0.. {
+            if frontier
+                .iter()
+                .any(|update| ordering.diagnose(update).unwrap().is_valid())
+            {
+                return moves;
+            }
+
+            let mut next = Vec::new();
+            for update in frontier {
+                for from in 0..update.len() {
+                    for to in 0..update.len() {
+                        let mut moved = update.clone();
+                        let page = moved.remove(from);
+                        moved.insert(to, page);
+                        if seen.insert(moved.clone()) {
+                            next.push(moved);
+                        }
+                    }
+                }
+            }
+            frontier = next;
+        }
+        unreachable!()
+    }
+
+    #[test]
+    fn diagnose_agrees_with_parts_test() {
+        let rejected = diagnose_updates("1|3\n\n3,2,1\n1,2,3").unwrap();
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].0, [3, 2, 1]);
+        assert_eq!(
+            rejected[0].1.as_ref().unwrap().violations,
+            [Violation {
+                before: 1,
+                after: 3,
+                before_position: 2,
+                after_position: 0,
+            }]
+        );
+        assert_eq!(part1("1|3\n\n3,2,1\n1,2,3"), Ok(2));
+
+        // a cycle between the pages of one update doesn't hide the other rejected updates
+        let rejected = diagnose_updates("1|2\n2|1\n3|4\n\n1,2\n4,3\n3,4\n").unwrap();
+        assert_eq!(rejected.len(), 2);
+        assert_eq!(rejected[0].0, [1, 2]);
+        assert_eq!(rejected[0].1.as_ref().unwrap_err().pages.len(), 2);
+        assert_eq!(rejected[1].0, [4, 3]);
+        assert_eq!(rejected[1].1.as_ref().map(|d| d.moves), Ok(1));
+        assert_eq!(
+            diagnose_updates("1|2\n\n2,1,\n"),
+            Err(ParseError {
+                kind: ParseErrorKind::InvalidPage,
+                line: 3,
+                column: 5,
+            })
+        );
+
+        let mut rng = TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let mut next = move |bound: u64| rng.random_range(0..bound);
+        for _ in 0..200 {
+            // Sparse rules only from smaller to larger pages, so there are no cycles.
+            let rules: String = (0..next(6) + 1)
+                .map(|_| {
+                    let (a, b) = (next(9), next(9));
+                    format!("{}|{}\n", a.min(b), a.max(b) + 1)
+                })
+                .collect();
+            let updates: Vec<Vec<u32>> = (0..5)
+                .map(|_| {
+                    let mut update: Vec<u32> = (0..10).collect();
+                    for i in (1..update.len()).rev() {
+                        update.swap(i, next(i as u64 + 1) as usize);
+                    }
+                    update.truncate(next(7) as usize + 1);
+                    update
+                })
+                .collect();
+            let lines: Vec<String> = updates
+                .iter()
+                .map(|update| {
+                    update
+                        .iter()
+                        .map(u32::to_string)
+                        .collect::<Vec<_>>()
+                        .join(",")
+                })
+                .collect();
+            let input = format!("{rules}\n{}", lines.join("\n"));
+
+            let ordering = PageOrdering::parse(&rules).unwrap();
+            let (unordered, ordered): (Vec<_>, Vec<_>) = updates
+                .iter()
+                .cloned()
+                .partition(|update| !ordering.is_ordered(update));
+            let rejected: Vec<Vec<u32>> = diagnose_updates(&input)
+                .unwrap()
+                .into_iter()
+                .map(|(update, _)| update)
+                .collect();
+            assert_eq!(rejected, unordered, "{input:?}");
+
+            let middle = |pages: &[u32]| pages[pages.len() / 2];
+            assert_eq!(
+                part1(&input),
+                Ok(ordered.iter().map(|update| middle(update)).sum::<u32>())
+            );
+            assert_eq!(
+                part2(&input),
+                Ok(unordered
+                    .iter()
+                    .map(|update| middle(&ordering.order(update).unwrap().pages))
+                    .sum::<u32>())
+            );
+        }
+    }
+
+    #[test]
+    fn diagnose_minimal_test() {
+        let mut rng = TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let mut next = move |bound: u64| rng.random_range(0..bound);
+
+        for _ in 0..200 {
+            // Rules only from smaller to larger pages can't form a cycle.
+            let rules: String = (0..next(12))
+                .map(|_| {
+                    let (a, b) = (next(7), next(7));
+                    format!("{}|{}\n", a.min(b), a.max(b) + 1)
+                })
+                .collect();
+            let ordering = PageOrdering::parse(&rules).unwrap();
+
+            let mut update: Vec<u32> = (0..9).collect();
+            for i in (1..update.len()).rev() {
+                update.swap(i, next(i as u64 + 1) as usize);
+            }
+            update.truncate(next(6) as usize + 1);
+
+            let diagnosis = ordering.diagnose(&update).unwrap();
+            assert_eq!(
+                diagnosis.moves,
+                brute_force_moves(&ordering, &update),
+                "{rules:?} {update:?}"
+            );
+            assert!(ordering.diagnose(&diagnosis.corrected).unwrap().is_valid());
+            assert_eq!(diagnosis.is_valid(), diagnosis.moves == 0);
+
+            // all but the moved pages are still in the same order
+            let mut common = vec![vec![0; update.len() + 1]; update.len() + 1];
+            for (i, a) in update.iter().enumerate() {
+                for (j, b) in diagnosis.corrected.iter().enumerate() {
+                    common[i + 1][j + 1] = if a == b {
+                        common[i][j] + 1
+                    } else {
+                        common[i][j + 1].max(common[i + 1][j])
+                    };
+                }
+            }
+            assert_eq!(
+                common[update.len()][update.len()],
+                update.len() - diagnosis.moves
+            );
+        }
     }
 }
@@ -3,7 +3,7 @@ use std::{
     fmt::Write,
 };
 
-use aoc_runner_derive::{aoc, aoc_generator};
+use aoc_runner_derive::aoc;
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 enum Direction {
@@ -66,6 +66,10 @@ impl VisitedGrid {
     pub fn len(&self) -> u32 {
         self.0.visited.len() as u32
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.visited.is_empty()
+    }
 }
 
 impl Grid {
@@ -113,15 +117,13 @@ impl Grid {
     #[inline(always)]
     pub fn coordinate_to_index(&self, coords: (usize, usize)) -> usize {
         let (row, column) = coords;
-        let index = row * self.width + column;
-        index
+        row * self.width + column
     }
 
     #[inline(always)]
     pub fn coordinate_to_index_width(width: usize, coords: (usize, usize)) -> usize {
         let (row, column) = coords;
-        let index = row * width + column;
-        index
+        row * width + column
     }
 
     pub fn drive_guard(mut self) -> VisitedGrid {
@@ -240,7 +242,7 @@ impl Grid {
         false
     }
 
-    fn find_obstructions(mut self) -> u32 {
+    fn find_obstructions(self) -> u32 {
         // populate visited
         let original = self.clone();
         let visited = self.drive_guard().0;
@@ -315,6 +317,7 @@ pub fn part2(input: &str) -> u32 {
     grid.find_obstructions()
 }
 
+#[cfg(test)]
 mod test {
     use crate::day6::Grid;
 
@@ -4,7 +4,7 @@ use arrayvec::ArrayVec;
 pub fn part1(input: &str) -> u64 {
     input
         .lines()
-        .map(|line| parse_line(line))
+        .map(parse_line)
         .filter_map(|(test, ops)| {
             if has_valid_permutation(test, &ops) {
                 Some(test)
@@ -19,7 +19,7 @@ pub fn part1(input: &str) -> u64 {
 pub fn part2(input: &str) -> u64 {
     input
         .lines()
-        .map(|line| parse_line(line))
+        .map(parse_line)
         .filter_map(|(test, ops)| {
             if has_valid_permutation_concat(test, &ops) {
                 Some(test)
@@ -35,7 +35,6 @@ fn parse_line(input: &str) -> (u64, Vec<u64>) {
     let test = test.parse::<u64>().unwrap();
     let operands = equation
         .trim_start_matches(":")
-        .trim()
         .split_whitespace()
         .map(|s| s.parse().unwrap())
         .collect();
@@ -114,10 +113,10 @@ fn has_valid_permutation_concat(test: u64, operands: &[u64]) -> bool {
 
 #[cfg(test)]
 mod test {
-    use crate::day7::{has_valid_permutation, part1, part2};
+    use crate::day7::{part1, part2};
 
     // 1708854043847 too low
-    use super::{concat, parse_line};
+    use super::concat;
 
     #[test]
     pub fn concat_test() {
@@ -3,46 +3,8 @@ use itertools::Itertools;
 use std::{
     collections::{HashMap, HashSet},
     fmt::Write,
-    hash::Hash,
 };
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
-enum Direction {
-    North,
-    South,
-    East,
-    West,
-    NorthEast,
-    NorthWest,
-    SouthEast,
-    SouthWest,
-}
-
-impl Direction {
-    #[inline(always)]
-    fn step(
-        self,
-        coords: (usize, usize),
-        width: usize,
-        height: usize,
-        step: usize,
-    ) -> Option<(usize, usize)> {
-        let (row, col) = coords;
-        let transformed = match self {
-            Direction::North => row.checked_sub(step).map(|row| (row, col)),
-            Direction::South => row.checked_add(step).map(|row| (row, col)),
-            Direction::East => col.checked_add(step).map(|col| (row, col)),
-            Direction::West => col.checked_sub(step).map(|col| (row, col)),
-            Direction::NorthEast => row.checked_sub(step).zip(col.checked_sub(step)),
-            Direction::NorthWest => row.checked_sub(step).zip(col.checked_add(step)),
-            Direction::SouthEast => row.checked_add(step).zip(col.checked_sub(step)),
-            Direction::SouthWest => row.checked_add(step).zip(col.checked_add(step)),
-        };
-
-        transformed.filter(|&(row, col)| row < height && col < width)
-    }
-}
-
 pub struct Map {
     towers: HashMap<usize, u8>,
     tower_index: HashMap<u8, Vec<usize>>,
@@ -58,6 +20,10 @@ impl AntinodeMap {
     pub fn len(&self) -> usize {
         self.0.antinodes.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.antinodes.is_empty()
+    }
 }
 
 impl Map {
@@ -65,13 +31,17 @@ impl Map {
         self.height * self.width
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     pub fn parse(input: &str) -> Map {
         let width = input.lines().next().unwrap().trim().len();
         let mut height = 0;
 
         let mut towers = HashMap::new();
         let mut tower_index: HashMap<u8, Vec<_>> = HashMap::new();
-        let mut antinodes = HashSet::new();
+        let antinodes = HashSet::new();
 
         // Can't be smart about it because the newlines throw off the indices.
         for line in input.lines() {
@@ -103,8 +73,7 @@ impl Map {
     }
 
     pub fn calculate_antinodes_part1(mut self) -> AntinodeMap {
-        let len = self.len();
-        for (frequency, indices) in self.tower_index.iter() {
+        for indices in self.tower_index.values() {
             if indices.len() == 1 {
                 continue;
             }
@@ -140,8 +109,7 @@ impl Map {
     }
 
     pub fn calculate_antinodes_part2(mut self) -> AntinodeMap {
-        let len = self.len();
-        for (frequency, indices) in self.tower_index.iter() {
+        for indices in self.tower_index.values() {
             if indices.len() == 1 {
                 continue;
             }
@@ -192,15 +160,13 @@ impl Map {
     #[inline(always)]
     pub fn coordinate_to_index(&self, coords: (usize, usize)) -> usize {
         let (row, column) = coords;
-        let index = row * self.width + column;
-        index
+        row * self.width + column
     }
 
     #[inline(always)]
     pub fn coordinate_to_index_width(width: usize, coords: (usize, usize)) -> usize {
         let (row, column) = coords;
-        let index = row * width + column;
-        index
+        row * width + column
     }
 }
 